@@ -0,0 +1,281 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Default groups of a `Child` directory.
+//!
+//! Every `Child` comes with two subdirectories that cannot be created or
+//! removed by the user: `params/`, holding the writable `priority`, and
+//! `stats/`, holding read-only counters. `kernel::configfs` has no support
+//! for default groups, so they are plain `config_group`s with C item types,
+//! like the link directory in `links.rs`. They are attached to the
+//! `config_group` of the new `Child` before `make_group` returns it, which is
+//! when configfs expects default groups to be present.
+//!
+//! The `Child` is reached from here as the parent of `params/`, which is also
+//! how its `active` attribute takes a configfs dependency on it.
+
+use {
+    crate::{
+        ChildNode, settings,
+        util::{Table, to_ssize},
+    },
+    core::{ffi::CStr, sync::atomic::Ordering},
+    kernel::{
+        alloc::kvec::KVec,
+        bindings, container_of,
+        error::{Error, Result, to_result},
+        ffi::c_char,
+        page::PAGE_SIZE,
+        str::CString,
+        sync::{
+            Arc,
+            lock::{Guard, mutex::MutexBackend},
+        },
+        types::Opaque,
+    },
+    pin_init::{PinInit, pin_data},
+};
+
+static PRIORITY_ATTRIBUTE: Table<bindings::configfs_attribute> =
+    Table(bindings::configfs_attribute {
+        ca_name: c"priority".as_ptr(),
+        ca_owner: core::ptr::null_mut(),
+        ca_mode: 0o644,
+        show: Some(show_priority),
+        store: Some(store_priority),
+    });
+
+static PARAMS_ATTRIBUTES: Table<[*mut bindings::configfs_attribute; 2]> = Table([
+    &raw const PRIORITY_ATTRIBUTE.0 as *mut bindings::configfs_attribute,
+    core::ptr::null_mut(),
+]);
+
+static PARAMS_TYPE: Table<bindings::config_item_type> = Table(bindings::config_item_type {
+    ct_owner: crate::THIS_MODULE.as_ptr(),
+    ct_item_ops: core::ptr::null(),
+    ct_group_ops: core::ptr::null(),
+    ct_attrs: &raw const PARAMS_ATTRIBUTES.0 as *mut *mut bindings::configfs_attribute,
+    ct_bin_attrs: core::ptr::null_mut(),
+});
+
+static GRAND_CHILDREN_ATTRIBUTE: Table<bindings::configfs_attribute> =
+    Table(bindings::configfs_attribute {
+        ca_name: c"grand_children".as_ptr(),
+        ca_owner: core::ptr::null_mut(),
+        ca_mode: 0o444,
+        show: Some(show_grand_children),
+        store: None,
+    });
+
+static WRITES_ATTRIBUTE: Table<bindings::configfs_attribute> =
+    Table(bindings::configfs_attribute {
+        ca_name: c"writes".as_ptr(),
+        ca_owner: core::ptr::null_mut(),
+        ca_mode: 0o444,
+        show: Some(show_writes),
+        store: None,
+    });
+
+static STATS_ATTRIBUTES: Table<[*mut bindings::configfs_attribute; 3]> = Table([
+    &raw const GRAND_CHILDREN_ATTRIBUTE.0 as *mut bindings::configfs_attribute,
+    &raw const WRITES_ATTRIBUTE.0 as *mut bindings::configfs_attribute,
+    core::ptr::null_mut(),
+]);
+
+static STATS_TYPE: Table<bindings::config_item_type> = Table(bindings::config_item_type {
+    ct_owner: crate::THIS_MODULE.as_ptr(),
+    ct_item_ops: core::ptr::null(),
+    ct_group_ops: core::ptr::null(),
+    ct_attrs: &raw const STATS_ATTRIBUTES.0 as *mut *mut bindings::configfs_attribute,
+    ct_bin_attrs: core::ptr::null_mut(),
+});
+
+/// The `params/` and `stats/` groups of one `Child`.
+///
+/// configfs unlinks default groups when their parent is removed, before the
+/// parent is released, so they only have to live as long as the `Child`.
+#[pin_data]
+pub(crate) struct Defaults {
+    #[pin]
+    params: Opaque<bindings::config_group>,
+    #[pin]
+    stats: Opaque<bindings::config_group>,
+    node: Arc<ChildNode>,
+}
+
+/// Initializes a zeroed `config_group` as a default group named `name`.
+fn group(
+    name: &'static CStr,
+    item_type: &'static Table<bindings::config_item_type>,
+) -> impl PinInit<Opaque<bindings::config_group>, Error> {
+    pin_init::zeroed().chain(
+        move |place: &mut Opaque<bindings::config_group>| -> Result {
+            // SAFETY: `place` is zeroed, which is a valid state for
+            // `config_group_init_type_name`, and `item_type` is static.
+            () = unsafe {
+                bindings::config_group_init_type_name(
+                    place.get(),
+                    name.as_ptr(),
+                    &raw const item_type.0,
+                )
+            };
+            Ok(())
+        },
+    )
+}
+
+impl Defaults {
+    pub(crate) fn new(node: Arc<ChildNode>) -> impl PinInit<Self, Error> {
+        kernel::try_pin_init!(Self {
+            params <- group(c"params", &PARAMS_TYPE),
+            stats <- group(c"stats", &STATS_TYPE),
+            node,
+        })
+    }
+
+    /// Adds the groups to the default groups of `parent`.
+    ///
+    /// # Safety
+    ///
+    /// `parent` must point to the `config_group` of a `Child` that has not
+    /// been returned to configfs yet and that owns `self`.
+    pub(crate) unsafe fn attach(&self, parent: *mut bindings::config_group) {
+        // SAFETY: By the safety requirements `parent` is valid and not yet
+        // visible to configfs, and both groups are initialized and pinned for
+        // as long as `parent` exists.
+        unsafe {
+            () = bindings::configfs_add_default_group(self.params.get(), parent);
+            () = bindings::configfs_add_default_group(self.stats.get(), parent);
+        }
+    }
+
+    /// Returns the item of the `Child` the groups were attached to.
+    ///
+    /// Only valid once configfs has linked the `Child` into the tree, which
+    /// happens before any of its attributes can be accessed.
+    fn parent(&self) -> *mut bindings::config_item {
+        // SAFETY: `params` is initialized, and configfs sets its parent when
+        // linking it and clears it when unlinking the `Child`.
+        unsafe { (*self.params.get()).cg_item.ci_parent }
+    }
+
+    /// Makes configfs refuse `rmdir` of the `Child` with `EBUSY` until
+    /// [`Defaults::undepend`] is called.
+    ///
+    /// Must be called from an attribute callback of the `Child`.
+    pub(crate) fn depend(&self) -> Result {
+        let item: *mut bindings::config_item = self.parent();
+        // SAFETY: The `Child` is linked while its attributes are accessed, so
+        // `item` is embedded in its live `config_group`, whose `cg_subsys` is
+        // set. The caller and the target are in the same subsystem, which
+        // cannot be unregistered while the callback pins its module, as
+        // `configfs_depend_item_unlocked` requires. It takes the locks it
+        // needs itself.
+        to_result(unsafe {
+            let group: *const bindings::config_group =
+                container_of!(item, bindings::config_group, cg_item);
+            bindings::configfs_depend_item_unlocked((*group).cg_subsys, item)
+        })
+    }
+
+    /// Drops the dependency taken by [`Defaults::depend`].
+    pub(crate) fn undepend(&self) {
+        // SAFETY: The `Child` is linked while its attributes are accessed and
+        // holds a dependency taken by `depend`.
+        () = unsafe { bindings::configfs_undepend_item(self.parent()) };
+    }
+
+    /// # Safety
+    ///
+    /// `item` must point to the item of the `params` group of a [`Defaults`].
+    unsafe fn from_params<'a>(item: *mut bindings::config_item) -> &'a Self {
+        // SAFETY: By the safety requirements, `item` is embedded in the
+        // `params` group of a live `Defaults`.
+        unsafe {
+            let group: *const bindings::config_group =
+                container_of!(item, bindings::config_group, cg_item);
+            &*container_of!(group.cast::<Opaque<bindings::config_group>>(), Self, params)
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `item` must point to the item of the `stats` group of a [`Defaults`].
+    unsafe fn from_stats<'a>(item: *mut bindings::config_item) -> &'a Self {
+        // SAFETY: By the safety requirements, `item` is embedded in the
+        // `stats` group of a live `Defaults`.
+        unsafe {
+            let group: *const bindings::config_group =
+                container_of!(item, bindings::config_group, cg_item);
+            &*container_of!(group.cast::<Opaque<bindings::config_group>>(), Self, stats)
+        }
+    }
+}
+
+unsafe extern "C" fn show_priority(item: *mut bindings::config_item, page: *mut c_char) -> isize {
+    // SAFETY: `PRIORITY_ATTRIBUTE` is only attached to `PARAMS_TYPE`, and
+    // configfs passes a buffer of `PAGE_SIZE` bytes.
+    let (this, page): (&Defaults, &mut [u8; PAGE_SIZE]) = unsafe {
+        (
+            Defaults::from_params(item),
+            &mut *page.cast::<[u8; PAGE_SIZE]>(),
+        )
+    };
+
+    let priority: u32 = *this.node.priority.lock();
+    to_ssize(settings::show(priority, page))
+}
+
+unsafe extern "C" fn store_priority(
+    item: *mut bindings::config_item,
+    page: *const c_char,
+    count: usize,
+) -> isize {
+    // SAFETY: `PRIORITY_ATTRIBUTE` is only attached to `PARAMS_TYPE`, and
+    // configfs passes `count` bytes at `page`.
+    let (this, page): (&Defaults, &[u8]) = unsafe {
+        (
+            Defaults::from_params(item),
+            core::slice::from_raw_parts(page.cast::<u8>(), count),
+        )
+    };
+
+    let ret: Result<usize> = settings::parse(page).map(|priority: u32| -> usize {
+        *this.node.priority.lock() = priority;
+        let _: u64 = this.node.writes.fetch_add(1, Ordering::Relaxed);
+        count
+    });
+    to_ssize(ret)
+}
+
+unsafe extern "C" fn show_grand_children(
+    item: *mut bindings::config_item,
+    page: *mut c_char,
+) -> isize {
+    // SAFETY: `GRAND_CHILDREN_ATTRIBUTE` is only attached to `STATS_TYPE`,
+    // and configfs passes a buffer of `PAGE_SIZE` bytes.
+    let (this, page): (&Defaults, &mut [u8; PAGE_SIZE]) = unsafe {
+        (
+            Defaults::from_stats(item),
+            &mut *page.cast::<[u8; PAGE_SIZE]>(),
+        )
+    };
+
+    let guard: Guard<'_, KVec<CString>, MutexBackend> = this.node.grand_children.lock();
+    to_ssize(settings::show(guard.len(), page))
+}
+
+unsafe extern "C" fn show_writes(item: *mut bindings::config_item, page: *mut c_char) -> isize {
+    // SAFETY: `WRITES_ATTRIBUTE` is only attached to `STATS_TYPE`, and
+    // configfs passes a buffer of `PAGE_SIZE` bytes.
+    let (this, page): (&Defaults, &mut [u8; PAGE_SIZE]) = unsafe {
+        (
+            Defaults::from_stats(item),
+            &mut *page.cast::<[u8; PAGE_SIZE]>(),
+        )
+    };
+
+    to_ssize(settings::show(
+        this.node.writes.load(Ordering::Relaxed),
+        page,
+    ))
+}
@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Symbolic link support for the Rust configfs sample.
+//!
+//! `kernel::configfs` does not wire up `allow_link`/`drop_link` yet, so the
+//! directory accepting links is a configfs subsystem built directly on top of
+//! the C API. All raw pointer handling of the sample is confined to this file.
+
+use {
+    crate::util::{Table, to_ssize},
+    core::pin::Pin,
+    kernel::{
+        alloc::{flags::GFP_KERNEL, kvec::KVec},
+        bindings, container_of,
+        error::{
+            Error, Result,
+            code::{EEXIST, EINVAL},
+            to_result,
+        },
+        ffi::{c_char, c_int},
+        new_mutex,
+        page::PAGE_SIZE,
+        pr_info,
+        str::{CStr, CString},
+        sync::{
+            Mutex,
+            lock::{Guard, mutex::MutexBackend},
+        },
+        types::Opaque,
+    },
+    pin_init::{PinInit, pin_data, pinned_drop},
+};
+
+/// Operations of a configfs directory that accepts symbolic links.
+pub(crate) trait LinkOperations {
+    /// Called before a link to `target` is created. Returning an error
+    /// refuses the `symlink(2)`.
+    fn allow_link(&self, target: &CStr) -> Result;
+
+    /// Called after the link to `target` has been removed.
+    fn drop_link(&self, target: &CStr);
+
+    /// Formats the linked set into `page` for the `links` attribute.
+    fn show_links(&self, page: &mut [u8; PAGE_SIZE]) -> Result<usize>;
+}
+
+/// Data of the link directory, the set of `Child` items linked into it.
+#[pin_data]
+pub(crate) struct Consumer {
+    #[pin]
+    links: Mutex<KVec<CString>>,
+}
+
+impl Consumer {
+    pub(crate) fn new() -> impl PinInit<Self, Error> {
+        kernel::try_pin_init!(Self {
+            links <- new_mutex!(KVec::new()),
+        })
+    }
+}
+
+impl LinkOperations for Consumer {
+    fn allow_link(&self, target: &CStr) -> Result {
+        pr_info!("Link {target}\n");
+        let mut guard: Guard<'_, KVec<CString>, MutexBackend> = self.links.lock();
+        if guard
+            .iter()
+            .any(|name: &CString| -> bool { name.as_bytes() == target.as_bytes() })
+        {
+            return Err(EEXIST);
+        }
+        () = guard.push(CString::try_from(target)?, GFP_KERNEL)?;
+        Ok(())
+    }
+
+    fn drop_link(&self, target: &CStr) {
+        pr_info!("Unlink {target}\n");
+        let mut guard: Guard<'_, KVec<CString>, MutexBackend> = self.links.lock();
+        () = guard.retain(|name: &mut CString| -> bool { name.as_bytes() != target.as_bytes() });
+    }
+
+    fn show_links(&self, page: &mut [u8; PAGE_SIZE]) -> Result<usize> {
+        pr_info!("Show links\n");
+        let guard: Guard<'_, KVec<CString>, MutexBackend> = self.links.lock();
+        let mut len: usize = 0;
+        for name in guard.iter() {
+            let data: &[u8] = name.as_bytes();
+            let end: usize = len + data.len() + 1;
+            if end > PAGE_SIZE {
+                break;
+            }
+            () = page[len..end - 1].copy_from_slice(data);
+            page[end - 1] = b'\n';
+            len = end;
+        }
+        Ok(len)
+    }
+}
+
+static LINKS_ATTRIBUTE: Table<bindings::configfs_attribute> = Table(bindings::configfs_attribute {
+    ca_name: c"links".as_ptr(),
+    ca_owner: core::ptr::null_mut(),
+    ca_mode: 0o444,
+    show: Some(show_links),
+    store: None,
+});
+
+static ATTRIBUTES: Table<[*mut bindings::configfs_attribute; 2]> = Table([
+    &raw const LINKS_ATTRIBUTE.0 as *mut bindings::configfs_attribute,
+    core::ptr::null_mut(),
+]);
+
+static ITEM_OPERATIONS: Table<bindings::configfs_item_operations> =
+    Table(bindings::configfs_item_operations {
+        release: None,
+        allow_link: Some(allow_link),
+        drop_link: Some(drop_link),
+    });
+
+static ITEM_TYPE: Table<bindings::config_item_type> = Table(bindings::config_item_type {
+    ct_owner: crate::THIS_MODULE.as_ptr(),
+    ct_item_ops: &raw const ITEM_OPERATIONS.0,
+    ct_group_ops: core::ptr::null(),
+    ct_attrs: &raw const ATTRIBUTES.0 as *mut *mut bindings::configfs_attribute,
+    ct_bin_attrs: core::ptr::null_mut(),
+});
+
+/// A configfs subsystem whose directory accepts symbolic links to the items
+/// created directly below the subsystem named `provider`.
+#[pin_data(PinnedDrop)]
+pub(crate) struct LinkSubsystem {
+    #[pin]
+    subsystem: Opaque<bindings::configfs_subsystem>,
+    provider: &'static CStr,
+    #[pin]
+    data: Consumer,
+}
+
+impl LinkSubsystem {
+    pub(crate) fn new(
+        name: &'static CStr,
+        provider: &'static CStr,
+        data: impl PinInit<Consumer, Error>,
+    ) -> impl PinInit<Self, Error> {
+        kernel::try_pin_init!(Self {
+            subsystem <- pin_init::zeroed().chain(
+                |place: &mut Opaque<bindings::configfs_subsystem>| -> Result {
+                    // SAFETY: `place` is zeroed, which is a valid state for
+                    // `config_group_init_type_name`, and `ITEM_TYPE` is static.
+                    () = unsafe {
+                        bindings::config_group_init_type_name(
+                            &raw mut (*place.get()).su_group,
+                            name.as_char_ptr(),
+                            &raw const ITEM_TYPE.0,
+                        )
+                    };
+
+                    // SAFETY: `su_mutex` is valid for use as a mutex.
+                    () = unsafe {
+                        bindings::__mutex_init(
+                            &raw mut (*place.get()).su_mutex,
+                            kernel::optional_name!().as_char_ptr(),
+                            kernel::static_lock_class!().as_ptr(),
+                        )
+                    };
+                    Ok(())
+                }
+            ),
+            provider,
+            data <- data,
+        })
+        .pin_chain(|this: Pin<&mut Self>| -> Result {
+            // SAFETY: `this.subsystem` was initialized according to the C API
+            // contract above and is pinned until `PinnedDrop` unregisters it.
+            to_result(unsafe { bindings::configfs_register_subsystem(this.subsystem.get()) })
+        })
+    }
+
+    /// # Safety
+    ///
+    /// `item` must point to the root item of a registered [`LinkSubsystem`].
+    unsafe fn from_item<'a>(item: *mut bindings::config_item) -> &'a Self {
+        // SAFETY: By the safety requirements, `item` is embedded in the
+        // `su_group` of the `configfs_subsystem` embedded in a `LinkSubsystem`.
+        unsafe {
+            let group: *const bindings::config_group =
+                container_of!(item, bindings::config_group, cg_item);
+            let subsystem: *const bindings::configfs_subsystem =
+                container_of!(group, bindings::configfs_subsystem, su_group);
+            &*container_of!(
+                subsystem.cast::<Opaque<bindings::configfs_subsystem>>(),
+                Self,
+                subsystem
+            )
+        }
+    }
+
+    /// Returns the name of `target` if it was created directly below the
+    /// provider subsystem.
+    ///
+    /// # Safety
+    ///
+    /// `target` must point to a valid `config_item` for the lifetime `'a`.
+    unsafe fn target_name<'a>(&self, target: *mut bindings::config_item) -> Option<&'a CStr> {
+        // SAFETY: By the safety requirements `target` is valid, and configfs
+        // keeps the group and subsystem of a live item valid.
+        unsafe {
+            let group: *mut bindings::config_group = (*target).ci_group;
+            if group.is_null() || (*group).cg_subsys.is_null() {
+                return None;
+            }
+
+            let root: *mut bindings::config_item = &raw mut (*(*group).cg_subsys).su_group.cg_item;
+            let root_name: &CStr = CStr::from_char_ptr((*root).ci_name);
+            if (*target).ci_parent != root || root_name.as_bytes() != self.provider.as_bytes() {
+                return None;
+            }
+
+            Some(CStr::from_char_ptr((*target).ci_name))
+        }
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for LinkSubsystem {
+    fn drop(self: Pin<&mut Self>) {
+        // SAFETY: The subsystem was registered in `LinkSubsystem::new`.
+        () = unsafe { bindings::configfs_unregister_subsystem(self.subsystem.get()) };
+    }
+}
+
+unsafe extern "C" fn allow_link(
+    item: *mut bindings::config_item,
+    target: *mut bindings::config_item,
+) -> c_int {
+    // SAFETY: `ITEM_TYPE` is only used by `LinkSubsystem`, so `item` is its
+    // root item, and configfs holds a reference on `target` during the call.
+    let (this, name): (&LinkSubsystem, Option<&CStr>) = unsafe {
+        let this: &LinkSubsystem = LinkSubsystem::from_item(item);
+        (this, this.target_name(target))
+    };
+
+    match name {
+        Some(name) => match this.data.allow_link(name) {
+            Ok(()) => 0,
+            Err(e) => e.to_errno(),
+        },
+        None => EINVAL.to_errno(),
+    }
+}
+
+unsafe extern "C" fn drop_link(
+    item: *mut bindings::config_item,
+    target: *mut bindings::config_item,
+) {
+    // SAFETY: See `allow_link`; only links accepted there reach this callback.
+    let (this, name): (&LinkSubsystem, Option<&CStr>) = unsafe {
+        let this: &LinkSubsystem = LinkSubsystem::from_item(item);
+        (this, this.target_name(target))
+    };
+
+    if let Some(name) = name {
+        () = this.data.drop_link(name);
+    }
+}
+
+unsafe extern "C" fn show_links(item: *mut bindings::config_item, page: *mut c_char) -> isize {
+    // SAFETY: `LINKS_ATTRIBUTE` is only attached to `ITEM_TYPE`, and configfs
+    // passes a buffer of `PAGE_SIZE` bytes.
+    let (this, page): (&LinkSubsystem, &mut [u8; PAGE_SIZE]) = unsafe {
+        (
+            LinkSubsystem::from_item(item),
+            &mut *page.cast::<[u8; PAGE_SIZE]>(),
+        )
+    };
+
+    to_ssize(this.data.show_links(page))
+}
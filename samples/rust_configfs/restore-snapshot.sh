@@ -0,0 +1,53 @@
+#!/bin/sh
+# SPDX-License-Identifier: GPL-2.0
+#
+# Restores a snapshot read from the `snapshot` attribute of rust_configfs,
+# e.g. one saved before a reboot. configfs directories can only be created by
+# mkdir(2), so every directory named in the snapshot is created first and the
+# snapshot is written back afterwards. Run as root with configfs mounted and
+# rust_configfs loaded:
+#
+#	cat /sys/kernel/config/rust_configfs/snapshot > saved
+#	./restore-snapshot.sh saved
+
+set -e
+
+ROOT=${ROOT:-/sys/kernel/config/rust_configfs}
+SNAPSHOT=$1
+
+if [ -z "$SNAPSHOT" ]; then
+	echo "usage: $0 SNAPSHOT" >&2
+	exit 1
+fi
+
+# Lines without a space name a directory. Names escape backslashes as `\\`,
+# newlines as `\n` and spaces as `\s`; names holding newlines cannot be passed
+# through the loop below and are refused.
+DIRS=$(awk '
+function unescape(name,    out, i, c) {
+	out = ""
+	for (i = 1; i <= length(name); i++) {
+		c = substr(name, i, 1)
+		if (c == "\\") {
+			c = substr(name, ++i, 1)
+			if (c == "s") {
+				c = " "
+			} else if (c == "n") {
+				print "cannot create " name >"/dev/stderr"
+				exit 1
+			}
+		}
+		out = out c
+	}
+	return out
+}
+!/ / && $0 != "/" { print unescape(substr($0, 2)) }
+' "$SNAPSHOT")
+
+printf '%s\n' "$DIRS" | while IFS= read -r dir; do
+	if [ -n "$dir" ]; then
+		mkdir -p "$ROOT/$dir"
+	fi
+done
+
+cat "$SNAPSHOT" > "$ROOT/snapshot"
@@ -3,11 +3,12 @@
 //! Rust configfs sample.
 
 use {
+    core::{pin::Pin, sync::atomic::AtomicU64},
     kernel::{
         InPlaceModule, ThisModule,
-        alloc::{flags::GFP_KERNEL, kbox::KBox},
-        c_str, configfs,
-        configfs::{AttributeOperations, Group, GroupOperations, ItemType, Subsystem},
+        alloc::{flags::GFP_KERNEL, kbox::KBox, kvec::KVec},
+        bindings, c_str, configfs,
+        configfs::{AttributeOperations, Group, GroupOperations, HasGroup, ItemType, Subsystem},
         configfs_attrs,
         error::{Error, Result},
         macros::{module, vtable},
@@ -16,20 +17,101 @@ use {
         pr_info,
         str::{CStr, CString},
         sync::{
-            Mutex,
+            Arc, ArcBorrow, Mutex,
             lock::{Guard, mutex::MutexBackend},
         },
     },
-    pin_init::{PinInit, pin_data},
+    pin_init::{PinInit, pin_data, pinned_drop},
 };
 
-// `pin_data` cannot handle structs without braces.
+mod defaults;
+mod links;
+mod settings;
+mod snapshot;
+mod util;
+
+/// Values of the read-only attributes.
+const MESSAGE: &CStr = c_str!("Hello World\n");
+const BAZ: &CStr = c_str!("Hello Baz\n");
+const GC: &CStr = c_str!("Hello GC\n");
+
+/// The `Child` directories of the subsystem, kept for `snapshot`.
+type Children = Mutex<KVec<Arc<ChildNode>>>;
+
+/// State of a `Child` directory shared with the subsystem.
 #[pin_data]
-struct GrandChild {}
+struct ChildNode {
+    name: CString,
+    #[pin]
+    grand_children: Mutex<KVec<CString>>,
+    #[pin]
+    settings: ChildSettings,
+    /// `params/priority`.
+    #[pin]
+    priority: Mutex<u32>,
+    /// `stats/writes`, the number of stores to `params/`.
+    writes: AtomicU64,
+}
+
+impl ChildNode {
+    fn new(name: CString) -> impl PinInit<Self, Error> {
+        kernel::try_pin_init!(Self {
+            name,
+            grand_children <- new_mutex!(KVec::new()),
+            settings <- ChildSettings::new(),
+            priority <- new_mutex!(0),
+            writes: AtomicU64::new(0),
+        })
+    }
+}
+
+// Define the item type of `Child` with data type `Child`, the hand-written
+// attributes `baz` and `active`, one attribute per setting and child group type
+// `GrandChild`. `mkdir` in the directory representing a `Child` will create
+// directories backed by the `GrandChild` type.
+settings::configfs_settings! {
+    container: Group<Child>,
+    data: Child,
+    child: GrandChild,
+    attributes: [
+        baz: 0,
+        active: 1,
+    ],
+    settings: |child| &child.node.settings,
+    /// Tunable settings of a `Child`.
+    struct ChildSettings {
+        #[attribute(2)] threshold: u32 = 16,
+        #[attribute(3)] enabled: bool = false,
+    }
+}
+
+#[pin_data(PinnedDrop)]
+struct GrandChild {
+    name: CString,
+    parent: Arc<ChildNode>,
+}
 
 impl GrandChild {
-    fn new() -> impl PinInit<Self, Error> {
-        kernel::try_pin_init!(Self {})
+    fn new(name: CString, parent: Arc<ChildNode>) -> impl PinInit<Self, Error> {
+        kernel::try_pin_init!(Self { name, parent }).pin_chain(|this: Pin<&mut Self>| -> Result {
+            let name: CString = this.name.to_cstring()?;
+            () = this.parent.grand_children.lock().push(name, GFP_KERNEL)?;
+            Ok(())
+        })
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for GrandChild {
+    fn drop(self: Pin<&mut Self>) {
+        pr_info!("Release grand child {}\n", self.name);
+        let mut guard: Guard<'_, KVec<CString>, MutexBackend> = self.parent.grand_children.lock();
+        if let Some(index) = guard
+            .iter()
+            .position(|name: &CString| -> bool { name.as_bytes() == self.name.as_bytes() })
+        {
+            let _ = guard.remove(index);
+        }
     }
 }
 
@@ -39,19 +121,52 @@ impl AttributeOperations<0> for GrandChild {
 
     fn show(_container: &GrandChild, page: &mut [u8; PAGE_SIZE]) -> Result<usize> {
         pr_info!("Show grand child\n");
-        let data: &[u8] = c"Hello GC\n".to_bytes();
+        let data: &[u8] = GC.as_bytes();
         () = page[0..data.len()].copy_from_slice(data);
         Ok(data.len())
     }
 }
 
-// `pin_data` cannot handle structs without braces.
-#[pin_data]
-struct Child {}
+#[pin_data(PinnedDrop)]
+struct Child {
+    node: Arc<ChildNode>,
+    children: Arc<Children>,
+    /// `params/` and `stats/`, which configfs refers to until the `Child` is
+    /// removed.
+    defaults: Pin<KBox<defaults::Defaults>>,
+    /// Whether the `Child` holds a configfs dependency on itself, which makes
+    /// `rmdir` fail with `EBUSY`.
+    #[pin]
+    active: Mutex<bool>,
+}
 
 impl Child {
-    fn new() -> impl PinInit<Self, Error> {
-        kernel::try_pin_init!(Self {})
+    fn new(
+        node: Arc<ChildNode>,
+        children: Arc<Children>,
+        defaults: Pin<KBox<defaults::Defaults>>,
+    ) -> impl PinInit<Self, Error> {
+        kernel::try_pin_init!(Self {
+            node,
+            children,
+            defaults,
+            active <- new_mutex!(false),
+        })
+        .pin_chain(|this: Pin<&mut Self>| -> Result {
+            () = this.children.lock().push(this.node.clone(), GFP_KERNEL)?;
+            Ok(())
+        })
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for Child {
+    fn drop(self: Pin<&mut Self>) {
+        pr_info!("Release child {}\n", self.node.name);
+        () = self
+            .children
+            .lock()
+            .retain(|node: &mut Arc<ChildNode>| -> bool { !Arc::ptr_eq(node, &self.node) });
     }
 }
 
@@ -61,12 +176,42 @@ impl AttributeOperations<0> for Child {
 
     fn show(_container: &Child, page: &mut [u8; PAGE_SIZE]) -> Result<usize> {
         pr_info!("Show baz\n");
-        let data: &[u8] = c"Hello Baz\n".to_bytes();
+        let data: &[u8] = BAZ.as_bytes();
         () = page[0..data.len()].copy_from_slice(data);
         Ok(data.len())
     }
 }
 
+#[vtable]
+impl AttributeOperations<1> for Child {
+    type Data = Child;
+
+    fn show(container: &Child, page: &mut [u8; PAGE_SIZE]) -> Result<usize> {
+        settings::show(*container.active.lock(), page)
+    }
+
+    fn store(container: &Child, page: &[u8]) -> Result {
+        let active: bool = settings::parse(page)?;
+        let mut guard: Guard<'_, bool, MutexBackend> = container.active.lock();
+        if *guard == active {
+            return Ok(());
+        }
+
+        if active {
+            () = container.defaults.depend()?;
+        } else {
+            () = container.defaults.undepend();
+        }
+        pr_info!(
+            "Child {} is {}\n",
+            container.node.name,
+            if active { "active" } else { "inactive" }
+        );
+        *guard = active;
+        Ok(())
+    }
+}
+
 #[vtable]
 impl GroupOperations for Child {
     type Child = GrandChild;
@@ -87,9 +232,13 @@ impl GroupOperations for Child {
         Ok(configfs::Group::new(
             CString::try_from(name)?,
             tpe,
-            GrandChild::new(),
+            GrandChild::new(CString::try_from(name)?, self.node.clone()),
         ))
     }
+
+    fn drop_item(&self, _child: ArcBorrow<'_, Group<GrandChild>>) {
+        pr_info!("Drop grand child item of {}\n", self.node.name);
+    }
 }
 
 #[pin_data]
@@ -97,13 +246,15 @@ struct Configuration {
     message: &'static CStr,
     #[pin]
     bar: Mutex<(KBox<[u8; PAGE_SIZE]>, usize)>,
+    children: Arc<Children>,
 }
 
 impl Configuration {
     fn new() -> impl PinInit<Self, Error> {
         kernel::try_pin_init!(Self {
-            message: c_str!("Hello World\n"),
+            message: MESSAGE,
             bar <- new_mutex!((KBox::new([0; PAGE_SIZE], GFP_KERNEL)?, 0)),
+            children: Arc::pin_init(new_mutex!(KVec::new()), GFP_KERNEL)?,
         })
     }
 }
@@ -143,24 +294,67 @@ impl AttributeOperations<1> for Configuration {
     }
 }
 
+#[vtable]
+impl AttributeOperations<2> for Configuration {
+    type Data = Configuration;
+
+    fn show(container: &Configuration, page: &mut [u8; PAGE_SIZE]) -> Result<usize> {
+        pr_info!("Show snapshot\n");
+        snapshot::save(container, page)
+    }
+
+    fn store(container: &Configuration, page: &[u8]) -> Result {
+        pr_info!("Store snapshot\n");
+        snapshot::restore(container, page)
+    }
+}
+
 #[vtable]
 impl GroupOperations for Configuration {
     type Child = Child;
 
     fn make_group(&self, name: &CStr) -> Result<impl PinInit<configfs::Group<Child>, Error>> {
-        // Define a group with data type `Child`, one attribute `baz` and child
-        // group type `GrandChild`. `mkdir` in the directory representing this
-        // group will create directories backed by the `GrandChild` type.
-        let tpe: &ItemType<Group<Child>, Child> = configfs_attrs! {
-            container: Group<Child>,
-            data: Child,
-            child: GrandChild,
-            attributes: [
-                baz: 0,
-            ],
-        };
+        // Define a group with the item type of `ChildSettings`.
+        let tpe: &ItemType<Group<Child>, Child> = ChildSettings::item_type();
 
-        Ok(Group::new(CString::try_from(name)?, tpe, Child::new()))
+        let node: Arc<ChildNode> =
+            Arc::pin_init(ChildNode::new(CString::try_from(name)?), GFP_KERNEL)?;
+        let defaults: Pin<KBox<defaults::Defaults>> =
+            KBox::pin_init(defaults::Defaults::new(node.clone()), GFP_KERNEL)?;
+        let attach: *const defaults::Defaults = &*defaults;
+
+        // The default groups `params/` and `stats/` must be attached before
+        // configfs sees the new group, so do it once the group is initialized.
+        Ok(Group::new(
+            CString::try_from(name)?,
+            tpe,
+            Child::new(node, self.children.clone(), defaults),
+        )
+        .pin_chain(move |group: Pin<&mut Group<Child>>| -> Result {
+            // SAFETY: `attach` points into the heap allocation now owned by
+            // the `Child` in `group`, which is not visible to configfs until
+            // this initializer returns.
+            () = unsafe {
+                let parent: *const bindings::config_group =
+                    <Group<Child> as HasGroup<Child>>::group(&*group);
+                (*attach).attach(parent.cast_mut())
+            };
+            Ok(())
+        }))
+    }
+
+    // configfs refuses `rmdir` of a `Child` with `-EBUSY` while it is
+    // `active` or linked into `rust_configfs_links`, so this is only reached
+    // once it has been deactivated and every link to it has been removed.
+    fn drop_item(&self, child: ArcBorrow<'_, Group<Child>>) {
+        // SAFETY: `child` is a live group, whose name configfs keeps until
+        // it is released.
+        let name: &CStr = unsafe {
+            let group: *const bindings::config_group =
+                <Group<Child> as HasGroup<Child>>::group(&*child);
+            CStr::from_char_ptr((*group).cg_item.ci_name)
+        };
+        pr_info!("Drop child item {name}\n");
     }
 }
 
@@ -168,16 +362,18 @@ impl GroupOperations for Configuration {
 struct RustConfigfs {
     #[pin]
     config: configfs::Subsystem<Configuration>,
+    #[pin]
+    links: links::LinkSubsystem,
 }
 
 impl InPlaceModule for RustConfigfs {
     fn init(_module: &'static ThisModule) -> impl PinInit<Self, Error> {
         pr_info!("Rust configfs sample (init)\n");
 
-        // Define a subsystem with the data type `Configuration`, two
-        // attributes, `message` and `bar` and child group type `Child`. `mkdir`
-        // in the directory representing this subsystem will create directories
-        // backed by the `Child` type.
+        // Define a subsystem with the data type `Configuration`, three
+        // attributes, `message`, `bar` and `snapshot`, and child group type
+        // `Child`. `mkdir` in the directory representing this subsystem will
+        // create directories backed by the `Child` type.
         let item_type: &ItemType<Subsystem<Configuration>, Configuration> = configfs_attrs! {
             container: configfs::Subsystem<Configuration>,
             data: Configuration,
@@ -185,6 +381,7 @@ impl InPlaceModule for RustConfigfs {
             attributes: [
                 message: 0,
                 bar: 1,
+                snapshot: 2,
             ],
         };
 
@@ -192,6 +389,13 @@ impl InPlaceModule for RustConfigfs {
             config <- configfs::Subsystem::new(
                 c_str!("rust_configfs"), item_type, Configuration::new()
             ),
+            // Define a second subsystem with one attribute, `links`. Its
+            // directory accepts symbolic links to the `Child` directories of
+            // `rust_configfs`, similar to functions linked into USB gadget
+            // configurations, and `links` lists the linked set.
+            links <- links::LinkSubsystem::new(
+                c_str!("rust_configfs_links"), c_str!("rust_configfs"), links::Consumer::new()
+            ),
         })
     }
 }
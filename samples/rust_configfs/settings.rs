@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Declarative configfs settings.
+//!
+//! [`configfs_settings!`] takes a struct whose fields are annotated with
+//! attribute indices and generates the struct, one `AttributeOperations`
+//! implementation per field and the `configfs_attrs!` item type listing them,
+//! so adding a setting takes a single line. The values of the hand-written
+//! attributes of the sample are shown and parsed the same way.
+
+use {
+    crate::util::PageWriter,
+    core::{
+        fmt::{self, Write},
+        str::Utf8Error,
+    },
+    kernel::{
+        error::{
+            Error, Result,
+            code::{EFBIG, EINVAL},
+        },
+        page::PAGE_SIZE,
+    },
+};
+
+/// A value that can be stored in a setting.
+///
+/// Values are shown with their `Display` implementation.
+pub(crate) trait Setting: Copy + fmt::Display {
+    /// Parses a value written to the attribute, without surrounding
+    /// whitespace.
+    fn parse(data: &str) -> Result<Self>;
+}
+
+macro_rules! impl_setting_int {
+    ($($t:ident)*) => ($(impl Setting for $t {
+        fn parse(data: &str) -> Result<Self> {
+            data.parse::<$t>()
+                .map_err(|_: core::num::ParseIntError| -> Error { EINVAL })
+        }
+    })*)
+}
+
+impl_setting_int! { u8 u16 u32 u64 usize i8 i16 i32 i64 isize }
+
+impl Setting for bool {
+    fn parse(data: &str) -> Result<Self> {
+        match data {
+            "1" | "y" | "Y" | "on" | "true" => Ok(true),
+            "0" | "n" | "N" | "off" | "false" => Ok(false),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+/// Parses `page` as written to an attribute.
+pub(crate) fn parse<T: Setting>(page: &[u8]) -> Result<T> {
+    let data: &str = core::str::from_utf8(page).map_err(|_: Utf8Error| -> Error { EINVAL })?;
+    T::parse(data.trim())
+}
+
+/// Formats `value` and a newline into `page`.
+pub(crate) fn show<T: Setting>(value: T, page: &mut [u8; PAGE_SIZE]) -> Result<usize> {
+    let mut writer: PageWriter<'_> = PageWriter::new(page);
+    () = writeln!(writer, "{value}").map_err(|_: fmt::Error| -> Error { EFBIG })?;
+    Ok(writer.len())
+}
+
+/// Defines a struct of settings and exposes every field as an attribute of
+/// the configfs data type `data`.
+///
+/// Each field holds a [`Setting`] behind a `Mutex`, starts out with its
+/// default value and is shown and stored through the attribute with the
+/// given index. `settings` maps a `data` reference to the struct. The struct
+/// gets an `item_type()` function returning the `configfs_attrs!` item type
+/// with the hand-written `attributes` and one attribute per field:
+///
+/// ```ignore
+/// configfs_settings! {
+///     container: Group<Child>,
+///     data: Child,
+///     child: GrandChild,
+///     attributes: [
+///         baz: 0,
+///     ],
+///     settings: |child| &child.settings,
+///     struct ChildSettings {
+///         #[attribute(1)] threshold: u32 = 16,
+///         #[attribute(2)] enabled: bool = false,
+///     }
+/// }
+/// ```
+macro_rules! configfs_settings {
+    (
+        container: $container:ty,
+        data: $data:ty,
+        $(child: $child:ty,)?
+        attributes: [$($attribute:ident: $attribute_index:literal),* $(,)?],
+        settings: |$this:ident| $settings:expr,
+        $(#[$meta:meta])*
+        struct $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                #[attribute($index:literal)] $field:ident: $ty:ty = $default:expr
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[::pin_init::pin_data]
+        struct $name {
+            $(
+                $(#[doc = $doc])*
+                #[pin]
+                $field: ::kernel::sync::Mutex<$ty>,
+            )*
+        }
+
+        impl $name {
+            fn new() -> impl ::pin_init::PinInit<Self, ::kernel::error::Error> {
+                ::kernel::try_pin_init!(Self {
+                    $($field <- ::kernel::new_mutex!($default),)*
+                })
+            }
+
+            fn item_type() -> &'static ::kernel::configfs::ItemType<$container, $data> {
+                ::kernel::configfs_attrs! {
+                    container: $container,
+                    data: $data,
+                    $(child: $child,)?
+                    attributes: [
+                        $($attribute: $attribute_index,)*
+                        $($field: $index,)*
+                    ],
+                }
+            }
+
+            /// Calls `f` with the name and current value of every setting.
+            fn save(
+                &self,
+                mut f: impl FnMut(&str, &dyn ::core::fmt::Display) -> ::kernel::error::Result,
+            ) -> ::kernel::error::Result {
+                $(() = f(stringify!($field), &*self.$field.lock())?;)*
+                Ok(())
+            }
+
+            /// Checks that `value` can be stored in the setting `name`.
+            fn check(name: &[u8], value: &[u8]) -> ::kernel::error::Result {
+                $(
+                    if name == stringify!($field).as_bytes() {
+                        let _: $ty = $crate::settings::parse(value)?;
+                        return Ok(());
+                    }
+                )*
+                Err(::kernel::error::code::EINVAL)
+            }
+
+            /// Stores `value` in the setting `name`.
+            fn store(&self, name: &[u8], value: &[u8]) -> ::kernel::error::Result {
+                $(
+                    if name == stringify!($field).as_bytes() {
+                        *self.$field.lock() = $crate::settings::parse(value)?;
+                        return Ok(());
+                    }
+                )*
+                Err(::kernel::error::code::EINVAL)
+            }
+        }
+
+        $(
+            #[::kernel::macros::vtable]
+            impl ::kernel::configfs::AttributeOperations<$index> for $data {
+                type Data = $data;
+
+                fn show(
+                    container: &$data,
+                    page: &mut [u8; ::kernel::page::PAGE_SIZE],
+                ) -> ::kernel::error::Result<usize> {
+                    let $this: &$data = container;
+                    let settings: &$name = $settings;
+                    let value: $ty = *settings.$field.lock();
+                    $crate::settings::show(value, page)
+                }
+
+                fn store(container: &$data, page: &[u8]) -> ::kernel::error::Result {
+                    let $this: &$data = container;
+                    let settings: &$name = $settings;
+                    *settings.$field.lock() = $crate::settings::parse(page)?;
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use configfs_settings;
@@ -0,0 +1,370 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Text snapshot of the Rust configfs sample tree.
+//!
+//! Every line of a snapshot holds the path of a directory, optionally
+//! followed by the name and value of one of its writable attributes:
+//!
+//! ```text
+//! / bar 42\n
+//! /foo
+//! /foo threshold 16
+//! /foo enabled false
+//! /foo/params priority 0
+//! /foo/qux
+//! ```
+//!
+//! Newlines and backslashes in names and values are escaped as `\n` and `\\`,
+//! and spaces in directory names as `\s`, so the path always ends at the first
+//! space of a line. Restoring a snapshot updates `bar`, the settings of every
+//! `Child` and the `priority` in its `params/` directory.
+//!
+//! Read-only attributes are left out, as restoring cannot change them. A
+//! snapshot still has to fit in the single page of a configfs attribute, which
+//! holds about 50 children with short names and no grand children. Reading
+//! the attribute fails with `EFBIG` beyond that.
+//!
+//! configfs directories can only be created by `mkdir(2)`, so every directory
+//! in the snapshot must exist before it is restored. `restore-snapshot.sh`
+//! creates them and then writes the snapshot, which also works after a reboot.
+
+use {
+    crate::{ChildNode, ChildSettings, Configuration, settings, util::PageWriter},
+    core::fmt::{self, Display, Write},
+    kernel::{
+        alloc::{flags::GFP_KERNEL, kbox::KBox, kvec::KVec},
+        error::{
+            Error, Result,
+            code::{EFBIG, EINVAL, ENOENT},
+        },
+        page::PAGE_SIZE,
+        pr_err,
+        str::{BStr, CString},
+        sync::{
+            Arc,
+            lock::{Guard, mutex::MutexBackend},
+        },
+    },
+};
+
+/// Longest name of a configfs directory.
+const NAME_MAX: usize = 255;
+
+/// Writes snapshot lines to a page, escaping names and values.
+struct Writer<'a>(PageWriter<'a>);
+
+impl Writer<'_> {
+    fn push(&mut self, data: &[u8]) -> Result {
+        self.0.push(data)
+    }
+
+    fn push_escaped(&mut self, data: &[u8]) -> Result {
+        for byte in data {
+            () = match byte {
+                b'\n' => self.push(b"\\n")?,
+                b'\\' => self.push(b"\\\\")?,
+                _ => self.push(core::slice::from_ref(byte))?,
+            };
+        }
+        Ok(())
+    }
+
+    /// Like [`Writer::push_escaped`], but also escapes spaces.
+    fn push_name(&mut self, name: &[u8]) -> Result {
+        for byte in name {
+            () = match byte {
+                b' ' => self.push(b"\\s")?,
+                _ => self.push_escaped(core::slice::from_ref(byte))?,
+            };
+        }
+        Ok(())
+    }
+
+    fn path(&mut self, path: &[&[u8]]) -> Result {
+        if path.is_empty() {
+            () = self.push(b"/")?;
+        }
+        for name in path {
+            () = self.push(b"/")?;
+            () = self.push_name(name)?;
+        }
+        Ok(())
+    }
+
+    /// Writes one line for the directory at `path`, with an attribute if
+    /// `attribute` holds its name and value.
+    fn line(&mut self, path: &[&[u8]], attribute: Option<(&[u8], &[u8])>) -> Result {
+        () = self.path(path)?;
+        if let Some((name, value)) = attribute {
+            () = self.push(b" ")?;
+            () = self.push(name)?;
+            () = self.push(b" ")?;
+            () = self.push_escaped(value)?;
+        }
+        self.push(b"\n")
+    }
+
+    /// Writes one line for the setting `name` of the directory at `path`.
+    fn setting(&mut self, path: &[&[u8]], name: &str, value: &dyn Display) -> Result {
+        () = self.path(path)?;
+        () = self.push(b" ")?;
+        () = self.push(name.as_bytes())?;
+        () = self.push(b" ")?;
+        () = write!(self, "{value}").map_err(|_: fmt::Error| -> Error { EFBIG })?;
+        self.push(b"\n")
+    }
+}
+
+impl Write for Writer<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_escaped(s.as_bytes())
+            .map_err(|_: Error| -> fmt::Error { fmt::Error })
+    }
+}
+
+/// Serializes the subsystem, every `Child` and `GrandChild` and their
+/// writable attribute values into `page`.
+pub(crate) fn save(config: &Configuration, page: &mut [u8; PAGE_SIZE]) -> Result<usize> {
+    let mut writer: Writer<'_> = Writer(PageWriter::new(page));
+
+    () = save_tree(config, &mut writer).inspect_err(|e: &Error| {
+        if *e == EFBIG {
+            pr_err!("Snapshot does not fit in {PAGE_SIZE} bytes, remove some children\n");
+        }
+    })?;
+
+    Ok(writer.0.len())
+}
+
+fn save_tree(config: &Configuration, writer: &mut Writer<'_>) -> Result {
+    {
+        let guard: Guard<'_, (KBox<[u8; PAGE_SIZE]>, usize), MutexBackend> = config.bar.lock();
+        () = writer.line(&[], Some((b"bar", &guard.0[0..guard.1])))?;
+    }
+
+    let children: Guard<'_, KVec<Arc<ChildNode>>, MutexBackend> = config.children.lock();
+    for node in children.iter() {
+        let child: &[u8] = node.name.as_bytes();
+        () = writer.line(&[child], None)?;
+        () = node
+            .settings
+            .save(|name: &str, value: &dyn Display| -> Result {
+                writer.setting(&[child], name, value)
+            })?;
+        () = writer.setting(&[child, b"params"], "priority", &*node.priority.lock())?;
+
+        let grand_children: Guard<'_, KVec<CString>, MutexBackend> = node.grand_children.lock();
+        for name in grand_children.iter() {
+            () = writer.line(&[child, name.as_bytes()], None)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A parsed snapshot line.
+struct Line<'a> {
+    path: &'a [u8],
+    attribute: Option<(&'a [u8], &'a [u8])>,
+}
+
+impl<'a> Line<'a> {
+    fn parse(line: &'a [u8]) -> Line<'a> {
+        let (path, rest): (&[u8], Option<&[u8]>) = split_space(line);
+        Line {
+            path,
+            attribute: rest.map(|rest: &[u8]| -> (&[u8], &[u8]) {
+                let (name, value): (&[u8], Option<&[u8]>) = split_space(rest);
+                (name, value.unwrap_or(b""))
+            }),
+        }
+    }
+}
+
+fn split_space(data: &[u8]) -> (&[u8], Option<&[u8]>) {
+    match data.iter().position(|byte: &u8| -> bool { *byte == b' ' }) {
+        Some(index) => (&data[..index], Some(&data[index + 1..])),
+        None => (data, None),
+    }
+}
+
+/// What a snapshot line updates.
+enum Target {
+    /// Nothing, the line names a directory or a read-only attribute.
+    None,
+    /// `bar` of the subsystem.
+    Bar,
+    /// A setting of a `Child`.
+    Setting(Arc<ChildNode>),
+    /// `params/priority` of a `Child`.
+    Priority(Arc<ChildNode>),
+}
+
+/// Checks that the directory at `path` exists and that `attribute` is one of
+/// its attributes with a valid value.
+fn resolve(
+    config: &Configuration,
+    path: &[u8],
+    attribute: Option<(&[u8], &[u8])>,
+) -> Result<Target> {
+    let path: &[u8] = path.strip_prefix(b"/").ok_or(EINVAL)?;
+    if path.is_empty() {
+        return match attribute {
+            None | Some((b"message", _)) => Ok(Target::None),
+            Some((b"bar", _)) => Ok(Target::Bar),
+            Some(_) => Err(EINVAL),
+        };
+    }
+
+    let mut names = path.split(|byte: &u8| -> bool { *byte == b'/' });
+    let (child, grand_child): (&[u8], Option<&[u8]>) =
+        match (names.next(), names.next(), names.next()) {
+            (Some(child), None, None) if !child.is_empty() => (child, None),
+            (Some(child), Some(grand_child), None)
+                if !child.is_empty() && !grand_child.is_empty() =>
+            {
+                (child, Some(grand_child))
+            }
+            _ => return Err(EINVAL),
+        };
+
+    let mut child_buf: [u8; NAME_MAX] = [0; NAME_MAX];
+    let len: usize = unescape(child, &mut child_buf)?;
+    let child: &[u8] = &child_buf[..len];
+    let mut grand_child_buf: [u8; NAME_MAX] = [0; NAME_MAX];
+    let grand_child: Option<&[u8]> = match grand_child {
+        Some(name) => {
+            let len: usize = unescape(name, &mut grand_child_buf)?;
+            Some(&grand_child_buf[..len])
+        }
+        None => None,
+    };
+
+    let children: Guard<'_, KVec<Arc<ChildNode>>, MutexBackend> = config.children.lock();
+    let node: &Arc<ChildNode> = children
+        .iter()
+        .find(|node: &&Arc<ChildNode>| -> bool { node.name.as_bytes() == child })
+        .ok_or(ENOENT)?;
+
+    match grand_child {
+        None => match attribute {
+            None | Some((b"baz", _)) => Ok(Target::None),
+            Some((name, value)) => {
+                () = ChildSettings::check(name, value)?;
+                Ok(Target::Setting(node.clone()))
+            }
+        },
+        Some(b"params") => match attribute {
+            None => Ok(Target::None),
+            Some((b"priority", value)) => {
+                let _: u32 = settings::parse(value)?;
+                Ok(Target::Priority(node.clone()))
+            }
+            Some(_) => Err(EINVAL),
+        },
+        Some(b"stats") => match attribute {
+            None | Some((b"grand_children" | b"writes", _)) => Ok(Target::None),
+            Some(_) => Err(EINVAL),
+        },
+        Some(grand_child) => {
+            let grand_children: Guard<'_, KVec<CString>, MutexBackend> = node.grand_children.lock();
+            if !grand_children
+                .iter()
+                .any(|name: &CString| -> bool { name.as_bytes() == grand_child })
+            {
+                return Err(ENOENT);
+            }
+            match attribute {
+                None | Some((b"gc", _)) => Ok(Target::None),
+                Some(_) => Err(EINVAL),
+            }
+        }
+    }
+}
+
+/// Reverses the escaping of `value` into `out`, returning the length.
+fn unescape(value: &[u8], out: &mut [u8]) -> Result<usize> {
+    let mut len: usize = 0;
+    let mut escaped: bool = false;
+    for &byte in value {
+        let byte: u8 = match (escaped, byte) {
+            (false, b'\\') => {
+                escaped = true;
+                continue;
+            }
+            (false, byte) => byte,
+            (true, b'n') => b'\n',
+            (true, b's') => b' ',
+            (true, b'\\') => b'\\',
+            (true, _) => return Err(EINVAL),
+        };
+        escaped = false;
+        *out.get_mut(len).ok_or(EFBIG)? = byte;
+        len += 1;
+    }
+    if escaped {
+        return Err(EINVAL);
+    }
+    Ok(len)
+}
+
+/// Applies the snapshot in `page` to the tree.
+///
+/// The whole snapshot is checked before anything is updated, so a snapshot
+/// naming a missing directory, an unknown attribute or an invalid value
+/// changes nothing. Values of read-only attributes are accepted and ignored.
+pub(crate) fn restore(config: &Configuration, page: &[u8]) -> Result {
+    let mut bar: Option<(KBox<[u8; PAGE_SIZE]>, usize)> = None;
+    let mut settings: KVec<(Arc<ChildNode>, &[u8], &[u8])> = KVec::new();
+    let mut priorities: KVec<(Arc<ChildNode>, u32)> = KVec::new();
+
+    for line in page
+        .split(|byte: &u8| -> bool { *byte == b'\n' })
+        .filter(|line: &&[u8]| -> bool { !line.is_empty() })
+        .map(Line::parse)
+    {
+        let target: Target = match resolve(config, line.path, line.attribute) {
+            Ok(target) => target,
+            Err(e) => {
+                pr_err!(
+                    "Snapshot line for {:?} rejected{}\n",
+                    BStr::from_bytes(line.path),
+                    if e == ENOENT {
+                        ", create missing directories first, e.g. with restore-snapshot.sh"
+                    } else {
+                        ""
+                    }
+                );
+                return Err(e);
+            }
+        };
+
+        match (target, line.attribute) {
+            (Target::Bar, Some((_name, value))) => {
+                let mut data: KBox<[u8; PAGE_SIZE]> = KBox::new([0; PAGE_SIZE], GFP_KERNEL)?;
+                let len: usize = unescape(value, &mut data[..])?;
+                bar = Some((data, len));
+            }
+            (Target::Setting(node), Some((name, value))) => {
+                () = settings.push((node, name, value), GFP_KERNEL)?;
+            }
+            (Target::Priority(node), Some((_name, value))) => {
+                let priority: u32 = settings::parse(value)?;
+                () = priorities.push((node, priority), GFP_KERNEL)?;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(bar) = bar {
+        *config.bar.lock() = bar;
+    }
+    for (node, name, value) in settings.iter() {
+        () = node.settings.store(name, value)?;
+    }
+    for (node, priority) in priorities.iter() {
+        *node.priority.lock() = *priority;
+    }
+
+    Ok(())
+}
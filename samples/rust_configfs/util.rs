@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Helpers shared by the modules of the Rust configfs sample.
+
+use {
+    core::fmt,
+    kernel::{
+        error::{Error, Result, code::EFBIG},
+        page::PAGE_SIZE,
+    },
+};
+
+/// Wrapper allowing C operation tables to live in statics.
+#[repr(transparent)]
+pub(crate) struct Table<T>(pub(crate) T);
+
+// SAFETY: The wrapped tables are never mutated and only hold pointers to
+// functions and other statics.
+unsafe impl<T> Sync for Table<T> {}
+
+/// Appends to a `PAGE_SIZE` buffer, failing with `EFBIG` once it is full.
+pub(crate) struct PageWriter<'a> {
+    page: &'a mut [u8; PAGE_SIZE],
+    len: usize,
+}
+
+impl<'a> PageWriter<'a> {
+    pub(crate) fn new(page: &'a mut [u8; PAGE_SIZE]) -> Self {
+        PageWriter { page, len: 0 }
+    }
+
+    /// Number of bytes written so far.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn push(&mut self, data: &[u8]) -> Result {
+        let end: usize = self.len + data.len();
+        if end > PAGE_SIZE {
+            return Err(EFBIG);
+        }
+        () = self.page[self.len..end].copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl fmt::Write for PageWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes())
+            .map_err(|_: Error| -> fmt::Error { fmt::Error })
+    }
+}
+
+/// Converts the result of a C attribute callback to what configfs expects.
+pub(crate) fn to_ssize(ret: Result<usize>) -> isize {
+    match ret {
+        Ok(len) => len as isize,
+        Err(e) => e.to_errno() as isize,
+    }
+}
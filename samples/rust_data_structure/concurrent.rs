@@ -0,0 +1,314 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Concurrent access to a shared table, with different synchronization.
+//!
+//! Several workers on the unbound system workqueue run a read-mostly mix of
+//! lookups, inserts and removals on one `RBTree<i32, u64>`, protected by:
+//!
+//! - a `Mutex`,
+//! - a `SpinLock`, with nodes allocated before taking it,
+//! - RCU: readers only mark a read-side critical section, and writers,
+//!   serialized by a `Mutex`, publish a modified copy of the tree and free the
+//!   old one after a grace period.
+//!
+//! Throughput of each scheme is logged. Enabled by the `workers` parameter:
+//!
+//! ```text
+//! insmod rust_data_structure.ko workers=4
+//! ```
+
+use {
+    core::{
+        pin::Pin,
+        sync::atomic::{AtomicPtr, AtomicU32, AtomicU64, Ordering},
+    },
+    kernel::{
+        alloc::{flags::GFP_KERNEL, kbox::KBox, kvec::KVec},
+        bindings,
+        error::Result,
+        impl_has_work, new_mutex, new_spinlock, new_work, pr_info,
+        rbtree::{RBTree, RBTreeNode, RBTreeNodeReservation},
+        sync::{
+            Arc, Completion, Mutex,
+            lock::{Backend, Guard, Lock, mutex::MutexBackend, spinlock::SpinLockBackend},
+            rcu,
+        },
+        workqueue::{self, Work, WorkItem},
+    },
+    pin_init::{PinInit, pin_data, pin_init, pinned_drop},
+};
+
+/// Operations run by every worker.
+const OPS_PER_WORKER: u32 = 10_000;
+
+/// Keys are in `[0, KEYS)`, and every other one is present initially.
+const KEYS: u32 = 1024;
+
+type Tree = RBTree<i32, u64>;
+
+/// A table shared by the workers.
+trait Table: Send + Sync + 'static {
+    fn lookup(&self, key: i32) -> Option<u64>;
+    fn insert(&self, key: i32, value: u64) -> Result;
+    fn remove(&self, key: i32) -> Result;
+}
+
+/// A table protected by a lock with backend `B`.
+#[pin_data]
+struct Locked<B: Backend> {
+    #[pin]
+    tree: Lock<Tree, B>,
+}
+
+impl<B: Backend + 'static> Table for Locked<B> {
+    fn lookup(&self, key: i32) -> Option<u64> {
+        self.tree.lock().get(&key).copied()
+    }
+
+    fn insert(&self, key: i32, value: u64) -> Result {
+        // Allocate outside of the lock, which may be a spinlock.
+        let reservation: RBTreeNodeReservation<i32, u64> = RBTree::try_reserve_node(GFP_KERNEL)?;
+        let node: RBTreeNode<i32, u64> = reservation.into_node(key, value);
+        let _: Option<RBTreeNode<i32, u64>> = self.tree.lock().insert(node);
+        Ok(())
+    }
+
+    fn remove(&self, key: i32) -> Result {
+        let _: Option<u64> = self.tree.lock().remove(&key);
+        Ok(())
+    }
+}
+
+/// A table read under RCU and replaced on every update.
+#[pin_data(PinnedDrop)]
+struct Rcu {
+    /// Serializes writers.
+    #[pin]
+    writer: Mutex<()>,
+    /// Current tree, from `KBox::into_raw`.
+    current: AtomicPtr<Tree>,
+}
+
+impl Rcu {
+    fn new(tree: Tree) -> Result<impl PinInit<Self>> {
+        let tree: KBox<Tree> = KBox::new(tree, GFP_KERNEL)?;
+        // The box is only turned into a raw pointer once the initializer
+        // runs, so it is freed if the initializer is dropped unused, e.g.
+        // because allocating the `Arc` failed.
+        Ok(pin_init!(Rcu {
+            writer <- new_mutex!(()),
+            current: AtomicPtr::new(KBox::into_raw(tree)),
+        }))
+    }
+
+    /// Publishes a copy of the current tree modified by `f`.
+    fn update(&self, f: impl FnOnce(&mut Tree) -> Result) -> Result {
+        let guard: Guard<'_, (), MutexBackend> = self.writer.lock();
+        let old: *mut Tree = self.current.load(Ordering::Relaxed);
+
+        // SAFETY: `old` is only freed by writers, which are serialized.
+        let mut copy: KBox<Tree> = KBox::new(copy(unsafe { &*old })?, GFP_KERNEL)?;
+        () = f(&mut copy)?;
+        self.current.store(KBox::into_raw(copy), Ordering::Release);
+        drop(guard);
+
+        // SAFETY: Readers that may still see `old` have left their read-side
+        // critical sections after the grace period, and later ones see the
+        // new tree.
+        unsafe {
+            () = bindings::synchronize_rcu();
+            drop(KBox::from_raw(old));
+        }
+        Ok(())
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for Rcu {
+    fn drop(self: Pin<&mut Self>) {
+        // SAFETY: There are no readers left, and `current` is from
+        // `KBox::into_raw`.
+        drop(unsafe { KBox::from_raw(self.current.load(Ordering::Relaxed)) });
+    }
+}
+
+impl Table for Rcu {
+    fn lookup(&self, key: i32) -> Option<u64> {
+        let _guard: rcu::Guard = rcu::read_lock();
+        // SAFETY: The tree is only freed after a grace period, so it outlives
+        // the read-side critical section.
+        let tree: &Tree = unsafe { &*self.current.load(Ordering::Acquire) };
+        tree.get(&key).copied()
+    }
+
+    fn insert(&self, key: i32, value: u64) -> Result {
+        self.update(|tree: &mut Tree| -> Result {
+            let _: Option<RBTreeNode<i32, u64>> =
+                tree.try_create_and_insert(key, value, GFP_KERNEL)?;
+            Ok(())
+        })
+    }
+
+    fn remove(&self, key: i32) -> Result {
+        self.update(|tree: &mut Tree| -> Result {
+            let _: Option<u64> = tree.remove(&key);
+            Ok(())
+        })
+    }
+}
+
+fn copy(tree: &Tree) -> Result<Tree> {
+    let mut copy: Tree = RBTree::new();
+    for (key, value) in tree.iter() {
+        let _: Option<RBTreeNode<i32, u64>> =
+            copy.try_create_and_insert(*key, *value, GFP_KERNEL)?;
+    }
+    Ok(copy)
+}
+
+fn populate() -> Result<Tree> {
+    let mut tree: Tree = RBTree::new();
+    for key in (0..KEYS as i32).step_by(2) {
+        let _: Option<RBTreeNode<i32, u64>> =
+            tree.try_create_and_insert(key, key as u64, GFP_KERNEL)?;
+    }
+    Ok(tree)
+}
+
+/// State shared by the workers of one run.
+#[pin_data]
+struct Run {
+    /// Workers still running.
+    remaining: AtomicU32,
+    /// Operations that failed to allocate.
+    failed: AtomicU64,
+    #[pin]
+    done: Completion,
+}
+
+/// One worker, running [`OPS_PER_WORKER`] operations on `table`.
+#[pin_data]
+struct Worker<T: Table> {
+    #[pin]
+    work: Work<Worker<T>>,
+    table: Arc<T>,
+    run: Arc<Run>,
+    /// State of the xorshift generator picking operations and keys.
+    seed: u32,
+}
+
+impl_has_work! {
+    impl{T: Table} HasWork<Self> for Worker<T> { self.work }
+}
+
+impl<T: Table> WorkItem for Worker<T> {
+    type Pointer = Arc<Worker<T>>;
+
+    fn run(this: Arc<Worker<T>>) {
+        let mut seed: u32 = this.seed;
+        let mut failed: u64 = 0;
+
+        for _ in 0..OPS_PER_WORKER {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+
+            let key: i32 = (seed % KEYS) as i32;
+            // 98% lookups, 1% inserts and 1% removals. Every update waits
+            // for a grace period with RCU, so more of them would only measure
+            // that.
+            let result: Result = match (seed >> 16) % 100 {
+                0 => this.table.insert(key, u64::from(seed)),
+                1 => this.table.remove(key),
+                _ => {
+                    let _: Option<u64> = this.table.lookup(key);
+                    Ok(())
+                }
+            };
+            if result.is_err() {
+                failed += 1;
+            }
+        }
+
+        let _: u64 = this.run.failed.fetch_add(failed, Ordering::Relaxed);
+        if this.run.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+            () = this.run.done.complete_all();
+        }
+    }
+}
+
+/// Runs `workers` workers on `table` and logs the throughput.
+fn bench<T: Table>(name: &str, table: Arc<T>, workers: u32) -> Result {
+    let run: Arc<Run> = Arc::pin_init(
+        pin_init!(Run {
+            remaining: AtomicU32::new(workers),
+            failed: AtomicU64::new(0),
+            done <- Completion::new(),
+        }),
+        GFP_KERNEL,
+    )?;
+
+    // Allocate all workers first, so that they start together.
+    let mut queued: KVec<Arc<Worker<T>>> = KVec::with_capacity(workers as usize, GFP_KERNEL)?;
+    for index in 0..workers {
+        () = queued.push(
+            Arc::pin_init(
+                pin_init!(Worker {
+                    work <- new_work!("Worker::work"),
+                    table: table.clone(),
+                    run: run.clone(),
+                    seed: 0x9e37_79b9 ^ (index + 1),
+                }),
+                GFP_KERNEL,
+            )?,
+            GFP_KERNEL,
+        )?;
+    }
+
+    // SAFETY: `ktime_get` has no preconditions.
+    let start: i64 = unsafe { bindings::ktime_get() };
+    for worker in queued.drain_all() {
+        // A new work item cannot be queued already.
+        let _: Result<(), Arc<Worker<T>>> = workqueue::system_unbound().enqueue(worker);
+    }
+    () = run.done.wait_for_completion();
+    // SAFETY: As above.
+    let elapsed_ns: i64 = unsafe { bindings::ktime_get() } - start;
+
+    let ops: u64 = u64::from(workers) * u64::from(OPS_PER_WORKER);
+    pr_info!(
+        "{name}: {ops} ops by {workers} workers in {} us, {} ops/ms, {} failed\n",
+        elapsed_ns / 1000,
+        ops * 1_000_000 / (elapsed_ns.max(1) as u64),
+        run.failed.load(Ordering::Relaxed)
+    );
+    Ok(())
+}
+
+/// Benchmarks every scheme with `workers` concurrent workers.
+pub(crate) fn run(workers: u32) -> Result {
+    if workers == 0 {
+        return Ok(());
+    }
+
+    let tree: Tree = populate()?;
+    let mutex: Arc<Locked<MutexBackend>> = Arc::pin_init(
+        pin_init!(Locked {
+            tree <- new_mutex!(tree),
+        }),
+        GFP_KERNEL,
+    )?;
+    () = bench("mutex", mutex, workers)?;
+
+    let tree: Tree = populate()?;
+    let spinlock: Arc<Locked<SpinLockBackend>> = Arc::pin_init(
+        pin_init!(Locked {
+            tree <- new_spinlock!(tree),
+        }),
+        GFP_KERNEL,
+    )?;
+    () = bench("spinlock", spinlock, workers)?;
+
+    let rcu: Arc<Rcu> = Arc::pin_init(Rcu::new(populate()?)?, GFP_KERNEL)?;
+    bench("rcu", rcu, workers)
+}
@@ -0,0 +1,214 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! debugfs interface of the Rust data structure sample.
+//!
+//! The map is exposed in `/sys/kernel/debug/rust_data_structure/`:
+//!
+//! - `entries`: lists the entries, one `<key> <value>` per line.
+//! - `control`: accepts `insert <key> <value>`, `remove <key>` and
+//!   `remove-range <start> <end>`.
+//! - `query`: lists the entries with a key in `[start, end)`, after
+//!   `<start> <end>` was written to it. All entries but `i32::MAX` initially.
+//!
+//! ```text
+//! echo "insert 42 answer" > /sys/kernel/debug/rust_data_structure/control
+//! echo "0 100" > /sys/kernel/debug/rust_data_structure/query
+//! cat /sys/kernel/debug/rust_data_structure/query
+//! ```
+
+use {
+    crate::range,
+    core::{fmt, num::ParseIntError, pin::Pin, str::Utf8Error},
+    kernel::{
+        alloc::{flags::GFP_KERNEL, kbox::KBox},
+        c_str,
+        debugfs::{Dir, File, Reader},
+        error::{
+            Error, Result,
+            code::{EINVAL, ENOENT},
+        },
+        new_mutex,
+        rbtree::{RBTree, RBTreeNode},
+        str::CString,
+        sync::{
+            Arc, Mutex,
+            lock::{Guard, mutex::MutexBackend},
+        },
+        uaccess::UserSliceReader,
+    },
+    pin_init::{PinInit, pin_data, pin_init},
+};
+
+/// The map shared by the module and its debugfs files.
+pub(crate) type Map = Mutex<RBTree<i32, CString>>;
+
+/// Longest command accepted by `control`.
+const MAX_COMMAND_LEN: usize = 128;
+
+fn parse_key(word: Option<&str>) -> Result<i32> {
+    word.ok_or(EINVAL)?
+        .parse::<i32>()
+        .map_err(|_: ParseIntError| -> Error { EINVAL })
+}
+
+/// Copies a command from userspace into `buf` and returns it, trimmed.
+fn read_command<'a>(
+    reader: &mut UserSliceReader,
+    buf: &'a mut [u8; MAX_COMMAND_LEN],
+) -> Result<&'a str> {
+    let len: usize = reader.len();
+    if len > buf.len() {
+        return Err(EINVAL);
+    }
+    () = reader.read_slice(&mut buf[..len])?;
+
+    let command: &str =
+        core::str::from_utf8(&buf[..len]).map_err(|_: Utf8Error| -> Error { EINVAL })?;
+    Ok(command.trim())
+}
+
+/// Contents of `entries`.
+struct Entries(Arc<Map>);
+
+impl fmt::Debug for Entries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let guard: Guard<'_, RBTree<i32, CString>, MutexBackend> = self.0.lock();
+        for (index, (key, value)) in guard.iter().enumerate() {
+            if index > 0 {
+                () = f.write_str("\n")?;
+            }
+            () = write!(f, "{key} {value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Commands written to `control`.
+struct Control(Arc<Map>);
+
+impl Control {
+    fn run(&self, command: &str) -> Result {
+        let mut words = command.split_ascii_whitespace();
+        let verb: &str = words.next().ok_or(EINVAL)?;
+        let key: i32 = parse_key(words.next())?;
+
+        match verb {
+            "insert" => {
+                let value: &str = words.next().ok_or(EINVAL)?;
+                if words.next().is_some() {
+                    return Err(EINVAL);
+                }
+                let value: CString = CString::try_from_fmt(kernel::fmt!("{value}"))?;
+                let _: Option<RBTreeNode<i32, CString>> = self
+                    .0
+                    .lock()
+                    .try_create_and_insert(key, value, GFP_KERNEL)?;
+                Ok(())
+            }
+            "remove" if words.next().is_none() => {
+                let _: CString = self.0.lock().remove(&key).ok_or(ENOENT)?;
+                Ok(())
+            }
+            "remove-range" => {
+                let end: i32 = parse_key(words.next())?;
+                if words.next().is_some() {
+                    return Err(EINVAL);
+                }
+                let _: usize = range::remove_in(&mut self.0.lock(), key, end);
+                Ok(())
+            }
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+impl Reader for Control {
+    fn read_from_slice(&self, reader: &mut UserSliceReader) -> Result {
+        let mut buf: [u8; MAX_COMMAND_LEN] = [0; MAX_COMMAND_LEN];
+        self.run(read_command(reader, &mut buf)?)
+    }
+}
+
+/// Contents of `query`, the entries in a range set by writing to it.
+#[pin_data]
+struct Query {
+    map: Arc<Map>,
+    /// `[start, end)`.
+    #[pin]
+    range: Mutex<(i32, i32)>,
+}
+
+impl Query {
+    fn new(map: Arc<Map>) -> impl PinInit<Self> {
+        pin_init!(Query {
+            map,
+            range <- new_mutex!((i32::MIN, i32::MAX)),
+        })
+    }
+}
+
+impl fmt::Debug for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (start, end): (i32, i32) = *self.range.lock();
+        let guard: Guard<'_, RBTree<i32, CString>, MutexBackend> = self.map.lock();
+        let mut first: bool = true;
+        range::for_each_in(
+            &guard,
+            start,
+            end,
+            |key: i32, value: &CString| -> fmt::Result {
+                () = f.write_str(if first { "" } else { "\n" })?;
+                first = false;
+                write!(f, "{key} {value}")
+            },
+        )
+    }
+}
+
+impl Reader for Query {
+    fn read_from_slice(&self, reader: &mut UserSliceReader) -> Result {
+        let mut buf: [u8; MAX_COMMAND_LEN] = [0; MAX_COMMAND_LEN];
+        let mut words = read_command(reader, &mut buf)?.split_ascii_whitespace();
+        let start: i32 = parse_key(words.next())?;
+        let end: i32 = parse_key(words.next())?;
+        if words.next().is_some() {
+            return Err(EINVAL);
+        }
+        *self.range.lock() = (start, end);
+        Ok(())
+    }
+}
+
+/// The debugfs directory and its files, removed on drop.
+pub(crate) struct Interface {
+    _entries: Pin<KBox<File<Entries>>>,
+    _control: Pin<KBox<File<Control>>>,
+    _query: Pin<KBox<File<Query>>>,
+    _dir: Dir,
+}
+
+impl Interface {
+    pub(crate) fn new(map: &Arc<Map>) -> Result<Self> {
+        let dir: Dir = Dir::new(c_str!("rust_data_structure"));
+
+        let entries: Pin<KBox<File<Entries>>> = KBox::pin_init(
+            dir.read_only_file(c_str!("entries"), Entries(map.clone())),
+            GFP_KERNEL,
+        )?;
+        let control: Pin<KBox<File<Control>>> = KBox::pin_init(
+            dir.write_only_file(c_str!("control"), Control(map.clone())),
+            GFP_KERNEL,
+        )?;
+        let query: Pin<KBox<File<Query>>> = KBox::pin_init(
+            dir.read_write_file(c_str!("query"), Query::new(map.clone())),
+            GFP_KERNEL,
+        )?;
+
+        Ok(Interface {
+            _entries: entries,
+            _control: control,
+            _query: query,
+            _dir: dir,
+        })
+    }
+}
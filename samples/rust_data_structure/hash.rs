@@ -0,0 +1,327 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A hash map with fallible allocations and incremental resizing.
+//!
+//! Entries are chained in buckets, whose number is a power of two. When the
+//! load factor would exceed 3/4, a table of twice as many buckets is
+//! allocated, and every later insertion or removal moves a few buckets of the
+//! old table to the new one, so no single operation rehashes everything.
+//! Lookups check both tables while this happens. Moving an entry only moves
+//! its node, so it cannot fail.
+//!
+//! The hasher is any `BuildHasher`, 64-bit FNV-1a by default. Nodes are
+//! allocated with `A`, `Kmalloc` by default, which lets tests inject
+//! allocation failures.
+
+use {
+    core::{
+        hash::{BuildHasher, BuildHasherDefault, Hash, Hasher},
+        mem,
+    },
+    kernel::{
+        alloc::{Allocator, Flags, allocator::Kmalloc, kbox::Box, kvec::KVec},
+        error::{Result, code::ENOMEM},
+    },
+};
+
+/// Buckets of the first table.
+const INITIAL_BUCKETS: usize = 8;
+
+/// Buckets of the old table moved by every insertion or removal.
+const MIGRATE_STEP: usize = 4;
+
+/// 64-bit FNV-1a.
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Builds [`Fnv1a`] hashers.
+pub(crate) type BuildFnv1a = BuildHasherDefault<Fnv1a>;
+
+struct Node<K, V, A: Allocator> {
+    hash: u64,
+    key: K,
+    value: V,
+    next: Link<K, V, A>,
+}
+
+type Link<K, V, A> = Option<Box<Node<K, V, A>, A>>;
+
+fn bucket<K, V, A: Allocator>(table: &KVec<Link<K, V, A>>, hash: u64) -> usize {
+    hash as usize & (table.len() - 1)
+}
+
+/// Returns the value of `key` in `table`.
+fn find<'a, K: Eq, V, A: Allocator>(
+    table: &'a KVec<Link<K, V, A>>,
+    hash: u64,
+    key: &K,
+) -> Option<&'a V> {
+    if table.is_empty() {
+        return None;
+    }
+
+    let mut link: &Link<K, V, A> = &table[bucket(table, hash)];
+    while let Some(node) = link {
+        if node.hash == hash && node.key == *key {
+            return Some(&node.value);
+        }
+        link = &node.next;
+    }
+    None
+}
+
+/// Returns the value of `key` in `table`, mutably.
+fn find_mut<'a, K: Eq, V, A: Allocator>(
+    table: &'a mut KVec<Link<K, V, A>>,
+    hash: u64,
+    key: &K,
+) -> Option<&'a mut V> {
+    if table.is_empty() {
+        return None;
+    }
+
+    let index: usize = bucket(table, hash);
+    let mut link: &mut Link<K, V, A> = &mut table[index];
+    while let Some(node) = link {
+        if node.hash == hash && node.key == *key {
+            return Some(&mut node.value);
+        }
+        link = &mut node.next;
+    }
+    None
+}
+
+/// Unlinks the node of `key` from `table`.
+fn unlink<K: Eq, V, A: Allocator>(
+    table: &mut KVec<Link<K, V, A>>,
+    hash: u64,
+    key: &K,
+) -> Option<Box<Node<K, V, A>, A>> {
+    if table.is_empty() {
+        return None;
+    }
+
+    let index: usize = bucket(table, hash);
+    let mut link: &mut Link<K, V, A> = &mut table[index];
+    loop {
+        let found: bool = match link {
+            None => return None,
+            Some(node) => node.hash == hash && node.key == *key,
+        };
+        if found {
+            let mut node: Box<Node<K, V, A>, A> = link.take()?;
+            *link = node.next.take();
+            return Some(node);
+        }
+        link = &mut link.as_mut()?.next;
+    }
+}
+
+/// Allocates a table of `buckets` empty buckets.
+fn alloc_table<K, V, A: Allocator>(buckets: usize, flags: Flags) -> Result<KVec<Link<K, V, A>>> {
+    let mut table: KVec<Link<K, V, A>> = KVec::with_capacity(buckets, flags)?;
+    for _ in 0..buckets {
+        // Cannot allocate, the capacity is reserved.
+        () = table.push(None, flags)?;
+    }
+    Ok(table)
+}
+
+/// A hash map from `K` to `V`, hashing keys with hashers built by `S` and
+/// allocating nodes with `A`.
+pub(crate) struct HashMap<K, V, S = BuildFnv1a, A: Allocator = Kmalloc> {
+    /// Table new entries go to, empty until the first insertion.
+    table: KVec<Link<K, V, A>>,
+    /// Table being moved to `table`, empty unless resizing.
+    old: KVec<Link<K, V, A>>,
+    /// Buckets of `old` before this one are empty.
+    migrated: usize,
+    len: usize,
+    hasher: S,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default, A: Allocator> HashMap<K, V, S, A> {
+    pub(crate) fn new() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher, A: Allocator> HashMap<K, V, S, A> {
+    /// Creates an empty map, which does not allocate until used.
+    pub(crate) fn with_hasher(hasher: S) -> Self {
+        HashMap {
+            table: KVec::new(),
+            old: KVec::new(),
+            migrated: 0,
+            len: 0,
+            hasher,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of buckets entries are added to.
+    pub(crate) fn buckets(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Whether entries are still being moved to a larger table.
+    pub(crate) fn is_resizing(&self) -> bool {
+        !self.old.is_empty()
+    }
+
+    fn hash(&self, key: &K) -> u64 {
+        self.hasher.hash_one(key)
+    }
+
+    /// Returns the value of `key`.
+    pub(crate) fn get(&self, key: &K) -> Option<&V> {
+        let hash: u64 = self.hash(key);
+        match find(&self.old, hash, key) {
+            Some(value) => Some(value),
+            None => find(&self.table, hash, key),
+        }
+    }
+
+    fn get_mut(&mut self, key: &K, hash: u64) -> Option<&mut V> {
+        match find_mut(&mut self.old, hash, key) {
+            Some(value) => Some(value),
+            None => find_mut(&mut self.table, hash, key),
+        }
+    }
+
+    /// Inserts `value` at `key` and returns the value it replaces. The map
+    /// is unchanged on failure.
+    pub(crate) fn insert(&mut self, key: K, value: V, flags: Flags) -> Result<Option<V>> {
+        let hash: u64 = self.hash(&key);
+        if let Some(current) = self.get_mut(&key, hash) {
+            return Ok(Some(mem::replace(current, value)));
+        }
+
+        let node: Box<Node<K, V, A>, A> = Box::new(
+            Node {
+                hash,
+                key,
+                value,
+                next: None,
+            },
+            flags,
+        )?;
+        () = self.try_reserve(1, flags)?;
+
+        () = self.migrate(MIGRATE_STEP);
+        () = self.link(node);
+        self.len += 1;
+        Ok(None)
+    }
+
+    /// Removes `key` and returns its value.
+    pub(crate) fn remove(&mut self, key: &K) -> Option<V> {
+        let hash: u64 = self.hash(key);
+        let node: Option<Box<Node<K, V, A>, A>> = match unlink(&mut self.old, hash, key) {
+            Some(node) => Some(node),
+            None => unlink(&mut self.table, hash, key),
+        };
+
+        () = self.migrate(MIGRATE_STEP);
+        let node: Box<Node<K, V, A>, A> = node?;
+        self.len -= 1;
+        Some(Box::into_inner(node).value)
+    }
+
+    /// Calls `f` on every entry, in no particular order.
+    pub(crate) fn for_each(&self, mut f: impl FnMut(&K, &V)) {
+        for mut link in self.old.iter().chain(self.table.iter()) {
+            while let Some(node) = link {
+                () = f(&node.key, &node.value);
+                link = &node.next;
+            }
+        }
+    }
+
+    /// Makes room for `additional` more entries without exceeding the load
+    /// factor, starting a resize if needed. The map is unchanged on failure.
+    pub(crate) fn try_reserve(&mut self, additional: usize, flags: Flags) -> Result {
+        let needed: usize = self
+            .len
+            .checked_add(additional)
+            .and_then(|len: usize| -> Option<usize> { len.checked_mul(4) })
+            .ok_or(ENOMEM)?
+            / 3;
+        if needed < self.table.len() {
+            return Ok(());
+        }
+
+        let buckets: usize = needed
+            .checked_add(1)
+            .and_then(usize::checked_next_power_of_two)
+            .ok_or(ENOMEM)?
+            .max(INITIAL_BUCKETS);
+        let table: KVec<Link<K, V, A>> = alloc_table(buckets, flags)?;
+
+        // Only one resize at a time, the previous one must complete first.
+        () = self.migrate(usize::MAX);
+        self.old = mem::replace(&mut self.table, table);
+        self.migrated = 0;
+        Ok(())
+    }
+
+    /// Adds `node` to the bucket of `table` for its hash.
+    fn link(&mut self, mut node: Box<Node<K, V, A>, A>) {
+        let index: usize = bucket(&self.table, node.hash);
+        node.next = self.table[index].take();
+        self.table[index] = Some(node);
+    }
+
+    /// Moves up to `buckets` buckets of `old` to `table`.
+    fn migrate(&mut self, buckets: usize) {
+        let end: usize = self.old.len().min(self.migrated.saturating_add(buckets));
+        while self.migrated < end {
+            let mut chain: Link<K, V, A> = self.old[self.migrated].take();
+            while let Some(mut node) = chain {
+                chain = node.next.take();
+                () = self.link(node);
+            }
+            self.migrated += 1;
+        }
+
+        if self.is_resizing() && self.migrated == self.old.len() {
+            self.old = KVec::new();
+            self.migrated = 0;
+        }
+    }
+}
+
+impl<K, V, S, A: Allocator> Drop for HashMap<K, V, S, A> {
+    fn drop(&mut self) {
+        // Dropping a node drops the rest of its chain recursively, so unlink
+        // the nodes one by one instead, as chains can be long with a poor
+        // hasher.
+        for link in self.old.iter_mut().chain(self.table.iter_mut()) {
+            let mut chain: Link<K, V, A> = link.take();
+            while let Some(mut node) = chain {
+                chain = node.next.take();
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A binary min-heap priority queue with handles.
+//!
+//! Items are kept in a `KVec` ordered as a binary heap, so the item with the
+//! lowest priority, e.g. the earliest deadline, comes out first. Pushing an
+//! item returns a [`Handle`] that can lower its priority later. Handles refer
+//! to slots that map them to positions in the heap, and the slots of popped
+//! items are reused, with a generation telling stale handles apart.
+//!
+//! Only [`PriorityQueue::push`] allocates.
+
+use kernel::{
+    alloc::{Flags, kvec::KVec},
+    error::{
+        Result,
+        code::{EINVAL, ENOENT},
+    },
+};
+
+/// Refers to an item pushed to a [`PriorityQueue`], until it is popped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Handle {
+    slot: usize,
+    generation: u32,
+}
+
+struct Entry<P, T> {
+    priority: P,
+    item: T,
+    slot: usize,
+}
+
+enum Slot {
+    /// Holds the item at `position` in the heap.
+    Used { position: usize, generation: u32 },
+    /// In the list of free slots, which continues at `next`.
+    Free {
+        next: Option<usize>,
+        generation: u32,
+    },
+}
+
+/// A priority queue of `T`, lowest `P` first.
+pub(crate) struct PriorityQueue<P: Ord, T> {
+    heap: KVec<Entry<P, T>>,
+    slots: KVec<Slot>,
+    /// First free slot.
+    free: Option<usize>,
+}
+
+impl<P: Ord, T> PriorityQueue<P, T> {
+    pub(crate) fn new() -> Self {
+        PriorityQueue {
+            heap: KVec::new(),
+            slots: KVec::new(),
+            free: None,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Adds `item` with `priority`. The queue is unchanged on failure.
+    pub(crate) fn push(&mut self, priority: P, item: T, flags: Flags) -> Result<Handle> {
+        () = self.heap.reserve(1, flags)?;
+        let slot: usize = match self.free {
+            Some(slot) => slot,
+            None => {
+                () = self.slots.push(
+                    Slot::Free {
+                        next: None,
+                        generation: 0,
+                    },
+                    flags,
+                )?;
+                self.slots.len() - 1
+            }
+        };
+
+        let position: usize = self.heap.len();
+        let generation: u32 = match self.slots[slot] {
+            Slot::Free { next, generation } => {
+                self.free = next;
+                generation
+            }
+            // Free slots are only taken from the list of free slots.
+            Slot::Used { .. } => return Err(EINVAL),
+        };
+        self.slots[slot] = Slot::Used {
+            position,
+            generation,
+        };
+
+        // Cannot allocate, the capacity is reserved.
+        () = self.heap.push(
+            Entry {
+                priority,
+                item,
+                slot,
+            },
+            flags,
+        )?;
+        () = self.sift_up(position);
+
+        Ok(Handle { slot, generation })
+    }
+
+    /// Returns the item with the lowest priority.
+    pub(crate) fn peek(&self) -> Option<(&P, &T)> {
+        self.heap
+            .first()
+            .map(|entry: &Entry<P, T>| -> (&P, &T) { (&entry.priority, &entry.item) })
+    }
+
+    /// Removes and returns the item with the lowest priority.
+    pub(crate) fn pop(&mut self) -> Option<(P, T)> {
+        let last: usize = self.heap.len().checked_sub(1)?;
+        () = self.swap(0, last);
+        let entry: Entry<P, T> = self.heap.pop()?;
+        if !self.heap.is_empty() {
+            () = self.sift_down(0);
+        }
+
+        let (Slot::Used { generation, .. } | Slot::Free { generation, .. }) =
+            self.slots[entry.slot];
+        self.slots[entry.slot] = Slot::Free {
+            next: self.free,
+            generation: generation.wrapping_add(1),
+        };
+        self.free = Some(entry.slot);
+
+        Some((entry.priority, entry.item))
+    }
+
+    /// Lowers the priority of the item of `handle` to `priority`.
+    ///
+    /// Fails with `ENOENT` if the item was popped, and with `EINVAL` if
+    /// `priority` is higher than the current one.
+    pub(crate) fn decrease_key(&mut self, handle: Handle, priority: P) -> Result {
+        let position: usize = match self.slots.get(handle.slot) {
+            Some(Slot::Used {
+                position,
+                generation,
+            }) if *generation == handle.generation => *position,
+            _ => return Err(ENOENT),
+        };
+
+        let entry: &mut Entry<P, T> = &mut self.heap[position];
+        if priority > entry.priority {
+            return Err(EINVAL);
+        }
+        entry.priority = priority;
+        () = self.sift_up(position);
+        Ok(())
+    }
+
+    /// Swaps the entries at `a` and `b` and updates their slots.
+    fn swap(&mut self, a: usize, b: usize) {
+        () = self.heap.swap(a, b);
+        for position in [a, b] {
+            if let Slot::Used { position: p, .. } = &mut self.slots[self.heap[position].slot] {
+                *p = position;
+            }
+        }
+    }
+
+    fn sift_up(&mut self, mut position: usize) {
+        while position > 0 {
+            let parent: usize = (position - 1) / 2;
+            if self.heap[parent].priority <= self.heap[position].priority {
+                break;
+            }
+            () = self.swap(parent, position);
+            position = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut position: usize) {
+        loop {
+            let mut smallest: usize = position;
+            for child in [2 * position + 1, 2 * position + 2] {
+                if child < self.heap.len()
+                    && self.heap[child].priority < self.heap[smallest].priority
+                {
+                    smallest = child;
+                }
+            }
+            if smallest == position {
+                break;
+            }
+            () = self.swap(position, smallest);
+            position = smallest;
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! An `XArray` index keyed like the map, and a benchmark against `RBTree`.
+//!
+//! An `XArray` is a radix tree indexed by `usize`, so it is compact and fast
+//! for dense IDs and wastes nodes on sparse ones. Negative keys cannot be
+//! stored.
+//!
+//! The Rust `XArray` has no iterator yet, so [`Index::for_each`] probes every
+//! index up to an end, which is the part of the comparison that favors the
+//! `RBTree` on sparse keys.
+//!
+//! The benchmark runs with the `index_keys` parameter set:
+//!
+//! ```text
+//! insmod rust_data_structure.ko index_keys=4096
+//! ```
+
+use {
+    core::{iter::StepBy, num::TryFromIntError, ops::Range, pin::Pin},
+    kernel::{
+        alloc::{flags::GFP_KERNEL, kbox::KBox},
+        bindings,
+        error::{Error, Result, code::EINVAL},
+        pr_info,
+        rbtree::{RBTree, RBTreeNode},
+        str::CString,
+        xarray::{AllocKind, Guard, XArray},
+    },
+};
+
+/// Distance between consecutive keys of the sparse benchmark.
+const SPARSE_STRIDE: i32 = 4099;
+
+/// Indices probed by [`Index::for_each`] without dropping the lock, which is
+/// a spinlock.
+const PROBE_BATCH: i32 = 4096;
+
+/// Values indexed by non-negative `i32` keys.
+pub(crate) struct Index(Pin<KBox<XArray<KBox<CString>>>>);
+
+impl Index {
+    pub(crate) fn new() -> Result<Self> {
+        Ok(Index(KBox::pin_init(
+            XArray::new(AllocKind::Alloc),
+            GFP_KERNEL,
+        )?))
+    }
+
+    fn index(key: i32) -> Result<usize> {
+        usize::try_from(key).map_err(|_: TryFromIntError| -> Error { EINVAL })
+    }
+
+    /// Stores `value` at `key`, returning the value it replaces.
+    pub(crate) fn insert(&self, key: i32, value: CString) -> Result<Option<KBox<CString>>> {
+        let index: usize = Self::index(key)?;
+        let value: KBox<CString> = KBox::new(value, GFP_KERNEL)?;
+        let mut guard: Guard<'_, KBox<CString>> = self.0.lock();
+        Ok(guard.store(index, value, GFP_KERNEL)?)
+    }
+
+    /// Calls `f` with the value at `key`, if any.
+    pub(crate) fn get<R>(&self, key: i32, f: impl FnOnce(Option<&CString>) -> R) -> R {
+        let guard: Guard<'_, KBox<CString>> = self.0.lock();
+        match Self::index(key) {
+            Ok(index) => f(guard.get(index)),
+            Err(_) => f(None),
+        }
+    }
+
+    /// Removes and returns the value at `key`.
+    pub(crate) fn remove(&self, key: i32) -> Option<KBox<CString>> {
+        let index: usize = Self::index(key).ok()?;
+        self.0.lock().remove(index)
+    }
+
+    /// Calls `f` on the entries with a key in `[0, end)`, in order.
+    pub(crate) fn for_each(&self, end: i32, mut f: impl FnMut(i32, &CString)) {
+        for batch in (0..end).step_by(PROBE_BATCH as usize) {
+            let guard: Guard<'_, KBox<CString>> = self.0.lock();
+            for key in batch..end.min(batch.saturating_add(PROBE_BATCH)) {
+                if let Some(value) = guard.get(key as usize) {
+                    () = f(key, value);
+                }
+            }
+        }
+    }
+}
+
+fn now() -> i64 {
+    // SAFETY: `ktime_get` has no preconditions.
+    unsafe { bindings::ktime_get() }
+}
+
+fn value(key: i32) -> Result<CString> {
+    CString::try_from_fmt(kernel::fmt!("{key}"))
+}
+
+/// Nanoseconds taken by every operation of a benchmark, and the number of
+/// entries iterated over.
+#[derive(Debug, Default)]
+struct Timings {
+    entries: usize,
+    insert: i64,
+    lookup: i64,
+    iterate: i64,
+    erase: i64,
+}
+
+fn bench_rbtree(keys: impl Iterator<Item = i32> + Clone) -> Result<Timings> {
+    let mut timings: Timings = Timings::default();
+    let mut tree: RBTree<i32, CString> = RBTree::new();
+
+    let start: i64 = now();
+    for key in keys.clone() {
+        let _: Option<RBTreeNode<i32, CString>> =
+            tree.try_create_and_insert(key, value(key)?, GFP_KERNEL)?;
+    }
+    timings.insert = now() - start;
+
+    let start: i64 = now();
+    for key in keys.clone() {
+        let _: Option<&CString> = tree.get(&key);
+    }
+    timings.lookup = now() - start;
+
+    let start: i64 = now();
+    timings.entries = tree.iter().count();
+    timings.iterate = now() - start;
+
+    let start: i64 = now();
+    for key in keys {
+        let _: Option<CString> = tree.remove(&key);
+    }
+    timings.erase = now() - start;
+
+    Ok(timings)
+}
+
+fn bench_xarray(keys: impl Iterator<Item = i32> + Clone, end: i32) -> Result<Timings> {
+    let mut timings: Timings = Timings::default();
+    let index: Index = Index::new()?;
+
+    let start: i64 = now();
+    for key in keys.clone() {
+        let _: Option<KBox<CString>> = index.insert(key, value(key)?)?;
+    }
+    timings.insert = now() - start;
+
+    let start: i64 = now();
+    for key in keys.clone() {
+        () = index.get(key, |_: Option<&CString>| {});
+    }
+    timings.lookup = now() - start;
+
+    let start: i64 = now();
+    () = index.for_each(end, |_: i32, _: &CString| timings.entries += 1);
+    timings.iterate = now() - start;
+
+    let start: i64 = now();
+    for key in keys {
+        let _: Option<KBox<CString>> = index.remove(key);
+    }
+    timings.erase = now() - start;
+
+    Ok(timings)
+}
+
+/// Compares `RBTree` and [`Index`] on `keys` dense and sparse keys.
+pub(crate) fn bench(keys: u32) -> Result {
+    if keys == 0 {
+        return Ok(());
+    }
+    let keys: i32 = i32::try_from(keys).map_err(|_: TryFromIntError| -> Error { EINVAL })?;
+    let sparse_end: i32 = keys.checked_mul(SPARSE_STRIDE).ok_or(EINVAL)?;
+
+    let dense: Range<i32> = 0..keys;
+    pr_info!("{keys} dense keys, in ns:\n");
+    pr_info!("  rbtree: {:?}\n", bench_rbtree(dense.clone())?);
+    pr_info!("  xarray: {:?}\n", bench_xarray(dense, keys)?);
+
+    let sparse: StepBy<Range<i32>> = (0..sparse_end).step_by(SPARSE_STRIDE as usize);
+    pr_info!("{keys} sparse keys, {SPARSE_STRIDE} apart, in ns:\n");
+    pr_info!("  rbtree: {:?}\n", bench_rbtree(sparse.clone())?);
+    pr_info!("  xarray: {:?}\n", bench_xarray(sparse, sparse_end)?);
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Items in an intrusive list and in the `RBTree` at the same time.
+//!
+//! Every [`Item`] embeds the links of the list, so adding it to the list
+//! allocates nothing, and is reference counted, so the tree can hold it too.
+//! The list keeps insertion order and the tree finds an item by key. Either
+//! way, an item is removed from the list through its own links, so a caller
+//! holding the handle returned by [`Items::push`] needs no lookup.
+//!
+//! Only the list side is free of allocations: `RBTree` is not intrusive, so
+//! [`Items::push`] allocates a tree node besides the item itself.
+
+use {
+    core::fmt,
+    kernel::{
+        alloc::flags::GFP_KERNEL,
+        error::Result,
+        list::{AtomicTracker, List, ListArc, ListLinks, impl_list_arc_safe, impl_list_item},
+        rbtree::{RBTree, RBTreeNode},
+        str::CString,
+        sync::{Arc, ArcBorrow},
+    },
+    pin_init::pin_data,
+};
+
+/// An entry of [`Items`].
+#[pin_data]
+pub(crate) struct Item {
+    pub(crate) key: i32,
+    pub(crate) value: CString,
+    #[pin]
+    links: ListLinks,
+    /// Ensures there is at most one `ListArc` of the item, the one held by
+    /// the list while it is in it.
+    #[pin]
+    tracker: AtomicTracker,
+}
+
+impl_list_arc_safe! {
+    impl ListArcSafe<0> for Item { tracked_by tracker: AtomicTracker; }
+}
+
+impl_list_item! {
+    impl ListItem<0> for Item { using ListLinks { self.links }; }
+}
+
+/// Items in insertion order, also indexed by key.
+pub(crate) struct Items {
+    list: List<Item>,
+    by_key: RBTree<i32, Arc<Item>>,
+}
+
+impl Items {
+    pub(crate) fn new() -> Self {
+        Items {
+            list: List::new(),
+            by_key: RBTree::new(),
+        }
+    }
+
+    /// Appends a new item, replacing the one with the same key, and returns a
+    /// handle to it.
+    pub(crate) fn push(&mut self, key: i32, value: CString) -> Result<Arc<Item>> {
+        let item: ListArc<Item> = ListArc::pin_init(
+            kernel::try_pin_init!(Item {
+                key,
+                value,
+                links <- ListLinks::new(),
+                tracker <- AtomicTracker::new(),
+            }),
+            GFP_KERNEL,
+        )?;
+
+        // Allocates the tree node. Nothing has changed yet if this fails.
+        let replaced: Option<RBTreeNode<i32, Arc<Item>>> =
+            self.by_key
+                .try_create_and_insert(key, item.clone_arc(), GFP_KERNEL)?;
+        if let Some(node) = replaced {
+            () = self.unlink(&node.into_value());
+        }
+
+        let handle: Arc<Item> = item.clone_arc();
+        () = self.list.push_back(item);
+        Ok(handle)
+    }
+
+    /// Removes the item with `key` and returns it.
+    pub(crate) fn remove(&mut self, key: i32) -> Option<Arc<Item>> {
+        let item: Arc<Item> = self.by_key.remove(&key)?;
+        () = self.unlink(&item);
+        Some(item)
+    }
+
+    /// Removes `item`, which was returned by [`Items::push`].
+    ///
+    /// Does nothing if it was already removed or replaced.
+    pub(crate) fn remove_item(&mut self, item: &Item) {
+        // SAFETY: Items are only ever in `self.list`, as they are created
+        // here and `ListArc`s of them never leave.
+        if let Some(_item) = unsafe { self.list.remove(item) } {
+            // Listed items are exactly those in `by_key`.
+            let _: Option<Arc<Item>> = self.by_key.remove(&item.key);
+        }
+    }
+
+    fn unlink(&mut self, item: &Item) {
+        // SAFETY: Items are only ever in `self.list`, as they are created
+        // here and `ListArc`s of them never leave.
+        let _: Option<ListArc<Item>> = unsafe { self.list.remove(item) };
+    }
+
+    /// Returns the item with `key`.
+    pub(crate) fn get(&self, key: i32) -> Option<&Arc<Item>> {
+        self.by_key.get(&key)
+    }
+
+    /// Iterates over the items in insertion order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = ArcBorrow<'_, Item>> {
+        self.list.iter()
+    }
+}
+
+impl fmt::Debug for Items {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list: fmt::DebugList<'_, '_> = f.debug_list();
+        for item in self.iter() {
+            let _: &mut fmt::DebugList<'_, '_> = list.entry(&(item.key, &item.value));
+        }
+        list.finish()
+    }
+}
@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A bounded cache evicting the least recently used entry.
+//!
+//! Entries are found by key in one `RBTree`, and a second one orders their
+//! keys by last use. Both lookups and updates of the recency are `O(log n)`,
+//! and a hit reuses the node of the recency tree instead of allocating.
+
+use kernel::{
+    alloc::flags::GFP_KERNEL,
+    error::{Result, code::EINVAL},
+    pr_info,
+    rbtree::{RBTree, RBTreeNode, RBTreeNodeReservation},
+    str::CString,
+};
+
+struct Entry {
+    value: CString,
+    /// Key of the entry in [`Lru::recency`].
+    stamp: u64,
+}
+
+/// Hit and miss counters of an [`Lru`].
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Stats {
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) evictions: u64,
+}
+
+/// A cache of at most `capacity` entries.
+pub(crate) struct Lru {
+    capacity: usize,
+    len: usize,
+    entries: RBTree<i32, Entry>,
+    /// Keys of `entries` by the time they were last used, oldest first.
+    recency: RBTree<u64, i32>,
+    /// Time of the next use.
+    clock: u64,
+    stats: Stats,
+}
+
+impl Lru {
+    pub(crate) fn new(capacity: usize) -> Result<Self> {
+        if capacity == 0 {
+            return Err(EINVAL);
+        }
+
+        Ok(Lru {
+            capacity,
+            len: 0,
+            entries: RBTree::new(),
+            recency: RBTree::new(),
+            clock: 0,
+            stats: Stats::default(),
+        })
+    }
+
+    /// Marks `key`, last used at `stamp`, as used now.
+    fn touch(&mut self, key: i32, stamp: u64) {
+        let now: u64 = self.clock;
+        self.clock += 1;
+
+        if let Some(node) = self.recency.remove_node(&stamp) {
+            let _: Option<RBTreeNode<u64, i32>> = self
+                .recency
+                .insert(node.into_reservation().into_node(now, key));
+        }
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.stamp = now;
+        }
+    }
+
+    fn stamp(&self, key: i32) -> Option<u64> {
+        self.entries
+            .get(&key)
+            .map(|entry: &Entry| -> u64 { entry.stamp })
+    }
+
+    /// Returns the value of `key`, which becomes the most recently used.
+    pub(crate) fn get(&mut self, key: i32) -> Option<&CString> {
+        let Some(stamp) = self.stamp(key) else {
+            self.stats.misses += 1;
+            return None;
+        };
+
+        self.stats.hits += 1;
+        () = self.touch(key, stamp);
+        self.entries
+            .get(&key)
+            .map(|entry: &Entry| -> &CString { &entry.value })
+    }
+
+    /// Inserts or replaces the value of `key`, evicting the least recently
+    /// used entry if the cache is full.
+    pub(crate) fn insert(&mut self, key: i32, value: CString) -> Result {
+        if let Some(stamp) = self.stamp(key) {
+            () = self.touch(key, stamp);
+            if let Some(entry) = self.entries.get_mut(&key) {
+                entry.value = value;
+            }
+            return Ok(());
+        }
+
+        // Allocate first, so that a failure does not evict anything.
+        let entry: RBTreeNodeReservation<i32, Entry> = RBTree::try_reserve_node(GFP_KERNEL)?;
+        let recent: RBTreeNodeReservation<u64, i32> = RBTree::try_reserve_node(GFP_KERNEL)?;
+
+        if self.len == self.capacity {
+            () = self.evict();
+        }
+
+        let stamp: u64 = self.clock;
+        self.clock += 1;
+        let _: Option<RBTreeNode<u64, i32>> = self.recency.insert(recent.into_node(stamp, key));
+        let _: Option<RBTreeNode<i32, Entry>> = self
+            .entries
+            .insert(entry.into_node(key, Entry { value, stamp }));
+        self.len += 1;
+
+        Ok(())
+    }
+
+    fn evict(&mut self) {
+        let Some(stamp) = self
+            .recency
+            .iter()
+            .next()
+            .map(|(stamp, _): (&u64, &i32)| -> u64 { *stamp })
+        else {
+            return;
+        };
+        let Some(key) = self.recency.remove(&stamp) else {
+            return;
+        };
+        if let Some(entry) = self.entries.remove(&key) {
+            pr_info!("lru: evicted {key} => {:?}\n", entry.value);
+        }
+        self.len -= 1;
+        self.stats.evictions += 1;
+    }
+
+    /// Removes `key` from the cache.
+    pub(crate) fn remove(&mut self, key: i32) -> Option<CString> {
+        let entry: Entry = self.entries.remove(&key)?;
+        let _: Option<i32> = self.recency.remove(&entry.stamp);
+        self.len -= 1;
+        Some(entry.value)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn stats(&self) -> Stats {
+        self.stats
+    }
+}
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Range queries over the map of the Rust data structure sample.
+//!
+//! `RBTree` has no range iterator, but its cursors can start at the first key
+//! not below a given one, which is enough to build bounds and half-open
+//! ranges on.
+
+use kernel::{
+    rbtree::{Cursor, CursorMut, RBTree, RBTreeNode},
+    str::CString,
+};
+
+/// Returns the first key not below `key`.
+pub(crate) fn lower_bound(tree: &RBTree<i32, CString>, key: i32) -> Option<i32> {
+    tree.cursor_lower_bound(&key)
+        .map(|cursor: Cursor<'_, i32, CString>| -> i32 { *cursor.current().0 })
+}
+
+/// Returns the first key above `key`.
+pub(crate) fn upper_bound(tree: &RBTree<i32, CString>, key: i32) -> Option<i32> {
+    let cursor: Cursor<'_, i32, CString> = tree.cursor_lower_bound(&key)?;
+    if *cursor.current().0 != key {
+        return Some(*cursor.current().0);
+    }
+    cursor
+        .move_next()
+        .map(|cursor: Cursor<'_, i32, CString>| -> i32 { *cursor.current().0 })
+}
+
+/// Calls `f` on the entries with a key in `[start, end)`, in order, stopping
+/// at the first error.
+pub(crate) fn for_each_in<E>(
+    tree: &RBTree<i32, CString>,
+    start: i32,
+    end: i32,
+    mut f: impl FnMut(i32, &CString) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut cursor: Option<Cursor<'_, i32, CString>> = tree.cursor_lower_bound(&start);
+    while let Some(current) = cursor {
+        let (key, value): (&i32, &CString) = current.current();
+        if *key >= end {
+            break;
+        }
+        () = f(*key, value)?;
+        cursor = current.move_next();
+    }
+    Ok(())
+}
+
+/// Removes the entries with a key in `[start, end)` and returns how many
+/// there were.
+pub(crate) fn remove_in(tree: &mut RBTree<i32, CString>, start: i32, end: i32) -> usize {
+    let mut removed: usize = 0;
+    let mut cursor: Option<CursorMut<'_, i32, CString>> = tree.cursor_lower_bound_mut(&start);
+    while let Some(current) = cursor {
+        if *current.current().0 >= end {
+            break;
+        }
+        // The cursor moves to the next entry, or the previous one if the
+        // removed entry was the last.
+        let (next, _node): (
+            Option<CursorMut<'_, i32, CString>>,
+            RBTreeNode<i32, CString>,
+        ) = current.remove_current();
+        removed += 1;
+        cursor = next
+            .filter(|next: &CursorMut<'_, i32, CString>| -> bool { *next.current().0 >= start });
+    }
+    removed
+}
@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Rust data structure sample.
+//!
+//! The map can be inspected and modified through debugfs, see [`debugfs`].
+
+use kernel::{
+    Module, ThisModule,
+    alloc::{flags::GFP_KERNEL, kbox::KBox, kvec::KVec},
+    c_str,
+    error::Result,
+    macros::module,
+    new_mutex, pr_cont, pr_info,
+    rbtree::{RBTree, RBTreeNode},
+    str::CString,
+    sync::{
+        Arc,
+        lock::{Guard, mutex::MutexBackend},
+    },
+};
+
+mod concurrent;
+mod debugfs;
+mod hash;
+mod heap;
+mod index;
+mod list;
+mod lru;
+mod range;
+
+struct RustDataStructure {
+    numbers: KVec<i32>,
+    map: Arc<debugfs::Map>,
+    items: list::Items,
+    _debugfs: debugfs::Interface,
+}
+
+impl Module for RustDataStructure {
+    fn init(_module: &'static ThisModule) -> Result<Self> {
+        pr_info!("Rust data structure sample (init)\n");
+
+        let mut numbers: KVec<i32> = KVec::new();
+        () = numbers.push(960, GFP_KERNEL)?;
+        () = numbers.push(110, GFP_KERNEL)?;
+        pr_info!(
+            "sum = {}",
+            numbers
+                .iter()
+                .fold::<i32, fn(i32, &i32) -> i32>(0, |acc: i32, x: &i32| -> i32 {
+                    acc.wrapping_add(*x)
+                })
+        );
+
+        let mut map: RBTree<i32, CString> = RBTree::new();
+        let _: Option<RBTreeNode<i32, CString>> =
+            map.try_create_and_insert(960, c_str!("Hello").to_cstring()?, GFP_KERNEL)?;
+        let _: Option<RBTreeNode<i32, CString>> =
+            map.try_create_and_insert(110, c_str!("Haven").to_cstring()?, GFP_KERNEL)?;
+        pr_info!(
+            "sum = {:?}",
+            map.iter().fold::<Option<i32>, _>(
+                Some(0),
+                |acc: Option<i32>, (key, _value): (&i32, &CString)| -> Option<i32> {
+                    acc?.checked_add(*key)
+                },
+            )
+        );
+
+        pr_info!(
+            "lower bound of 500 = {:?}, upper bound of 960 = {:?}\n",
+            range::lower_bound(&map, 500),
+            range::upper_bound(&map, 960)
+        );
+        pr_info!("Entries with a key in [0, 500):\n");
+        let _: Result = range::for_each_in(&map, 0, 500, |key: i32, value: &CString| -> Result {
+            pr_cont!("\t({key:?} => {value:?})\n");
+            Ok(())
+        });
+
+        let index: index::Index = index::Index::new()?;
+        for (key, value) in map.iter() {
+            let _: Option<KBox<CString>> = index.insert(*key, value.to_cstring()?)?;
+        }
+        () = index.get(960, |value: Option<&CString>| {
+            pr_info!("index[960] = {value:?}\n");
+        });
+        () = index::bench(*module_parameters::index_keys.value())?;
+
+        let mut items: list::Items = list::Items::new();
+        for (key, value) in map.iter() {
+            let _: Arc<list::Item> = items.push(*key, value.to_cstring()?)?;
+        }
+        let answer: Arc<list::Item> = items.push(42, c_str!("Answer").to_cstring()?)?;
+        if let Some(item) = items.get(110) {
+            pr_info!("items[110] = {:?}\n", item.value);
+        }
+        let _: Option<Arc<list::Item>> = items.remove(110);
+        () = items.remove_item(&answer);
+        pr_info!("items = {items:?}\n");
+
+        let mut lru: lru::Lru = lru::Lru::new(*module_parameters::lru_capacity.value() as usize)?;
+        for key in [1, 2, 3, 1, 4, 5, 1, 2, 6] {
+            if lru.get(key).is_none() {
+                () = lru.insert(key, CString::try_from_fmt(kernel::fmt!("value {key}"))?)?;
+            }
+        }
+        let _: Option<CString> = lru.remove(1);
+        pr_info!("lru: {} entries, {:?}\n", lru.len(), lru.stats());
+
+        let mut hash: hash::HashMap<i32, CString> = hash::HashMap::new();
+        () = hash.try_reserve(2, GFP_KERNEL)?;
+        for (key, value) in map.iter() {
+            let _: Option<CString> = hash.insert(*key, value.to_cstring()?, GFP_KERNEL)?;
+        }
+        pr_info!("hash[960] = {:?}\n", hash.get(&960));
+        let _: Option<CString> = hash.remove(&110);
+        pr_info!(
+            "hash: {} entries in {} buckets, resizing: {}\n",
+            hash.len(),
+            hash.buckets(),
+            hash.is_resizing()
+        );
+        () = hash.for_each(|key: &i32, value: &CString| {
+            pr_cont!("\t({key:?} => {value:?})\n");
+        });
+
+        // Items due after some milliseconds, run in order of their deadlines.
+        // SAFETY: `ktime_get` has no preconditions.
+        let now: i64 = unsafe { kernel::bindings::ktime_get() };
+        let mut queue: heap::PriorityQueue<i64, &str> = heap::PriorityQueue::new();
+        let _: heap::Handle = queue.push(now + 30 * 1_000_000, "flush", GFP_KERNEL)?;
+        let _: heap::Handle = queue.push(now + 10 * 1_000_000, "poll", GFP_KERNEL)?;
+        let report: heap::Handle = queue.push(now + 50 * 1_000_000, "report", GFP_KERNEL)?;
+        () = queue.decrease_key(report, now + 5 * 1_000_000)?;
+        if let Some((_, item)) = queue.peek() {
+            pr_info!("{} timed items, {item} first:\n", queue.len());
+        }
+        while let Some((deadline, item)) = queue.pop() {
+            pr_cont!("\t{item} at +{} ms\n", (deadline - now) / 1_000_000);
+        }
+
+        () = concurrent::run(*module_parameters::workers.value())?;
+
+        let map: Arc<debugfs::Map> = Arc::pin_init(new_mutex!(map), GFP_KERNEL)?;
+        let debugfs: debugfs::Interface = debugfs::Interface::new(&map)?;
+
+        Ok(RustDataStructure {
+            numbers,
+            map,
+            items,
+            _debugfs: debugfs,
+        })
+    }
+}
+
+impl Drop for RustDataStructure {
+    fn drop(&mut self) {
+        pr_info!("My numbers are {:?}\n", self.numbers);
+        pr_info!("My entries are:\n");
+        let map: Guard<'_, RBTree<i32, CString>, MutexBackend> = self.map.lock();
+        for (key, value) in map.iter() {
+            pr_cont!("\t({key:?} => {value:?})\n");
+        }
+        pr_info!("My items are {:?}\n", self.items);
+        pr_info!("Rust data structure sample (exit)\n");
+    }
+}
+
+module! {
+    type: RustDataStructure,
+    name: "rust_data_structure",
+    authors: ["Rick Yang"],
+    description: "Rust data structure sample",
+    license: "GPL",
+    params: {
+        workers: u32 {
+            default: 0,
+            description: "Number of workers benchmarking concurrent access, 0 to skip it",
+        },
+        lru_capacity: u32 {
+            default: 4,
+            description: "Capacity of the LRU cache",
+        },
+        index_keys: u32 {
+            default: 0,
+            description: "Number of keys comparing XArray and RBTree, 0 to skip it",
+        },
+    },
+}
@@ -0,0 +1,255 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Rust data structure tests
+
+use kernel::macros::kunit_tests;
+
+mod hash;
+mod heap;
+
+#[kunit_tests(rust_data_structure)]
+mod tests {
+    use {
+        crate::{
+            hash::{BuildFnv1a, HashMap},
+            heap::{Handle, PriorityQueue},
+        },
+        core::{
+            alloc::Layout,
+            hash::{BuildHasherDefault, Hasher},
+            ptr::NonNull,
+            sync::atomic::{AtomicBool, Ordering},
+        },
+        kernel::{
+            alloc::{
+                AllocError, Allocator, Flags, NumaNode,
+                allocator::Kmalloc,
+                flags::{__GFP_NOWARN, GFP_KERNEL},
+            },
+            error::{
+                Result,
+                code::{EINVAL, ENOENT, ENOMEM},
+            },
+        },
+    };
+
+    /// Hashes every key to the same value.
+    #[derive(Default)]
+    struct Constant;
+
+    impl Hasher for Constant {
+        fn write(&mut self, _bytes: &[u8]) {}
+
+        fn finish(&self) -> u64 {
+            0
+        }
+    }
+
+    /// Makes every allocation of [`Failing`] fail while set.
+    static FAIL: AtomicBool = AtomicBool::new(false);
+
+    /// `Kmalloc`, unless [`FAIL`] is set.
+    struct Failing;
+
+    // SAFETY: Allocations are forwarded to `Kmalloc`, or fail without
+    // touching `ptr`. Freeing, a reallocation to size zero, never fails.
+    unsafe impl Allocator for Failing {
+        const MIN_ALIGN: usize = Kmalloc::MIN_ALIGN;
+
+        unsafe fn realloc(
+            ptr: Option<NonNull<u8>>,
+            layout: Layout,
+            old_layout: Layout,
+            flags: Flags,
+            nid: NumaNode,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            if layout.size() != 0 && FAIL.load(Ordering::Relaxed) {
+                return Err(AllocError);
+            }
+            // SAFETY: By the safety requirements of this function.
+            unsafe { Kmalloc::realloc(ptr, layout, old_layout, flags, nid) }
+        }
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut map: HashMap<i32, i32> = HashMap::new();
+
+        assert_eq!(map.insert(960, 1, GFP_KERNEL), Ok(None));
+        assert_eq!(map.insert(110, 2, GFP_KERNEL), Ok(None));
+        assert_eq!(map.insert(960, 3, GFP_KERNEL), Ok(Some(1)));
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.get(&960), Some(&3));
+        assert_eq!(map.get(&110), Some(&2));
+        assert_eq!(map.get(&-1), None);
+
+        assert_eq!(map.remove(&960), Some(3));
+        assert_eq!(map.remove(&960), None);
+        assert_eq!(map.get(&960), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_collisions() {
+        let mut map: HashMap<i32, i32, BuildHasherDefault<Constant>> =
+            HashMap::with_hasher(BuildHasherDefault::default());
+
+        for key in 0..32 {
+            assert_eq!(map.insert(key, -key, GFP_KERNEL), Ok(None));
+        }
+        for key in 0..32 {
+            assert_eq!(map.get(&key), Some(&-key));
+        }
+
+        // Remove from the middle and both ends of the chain.
+        for key in [16, 0, 31] {
+            assert_eq!(map.remove(&key), Some(-key));
+        }
+        for key in 0..32 {
+            let expected: Option<i32> = (![16, 0, 31].contains(&key)).then_some(-key);
+            assert_eq!(map.get(&key).copied(), expected);
+        }
+        assert_eq!(map.len(), 29);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut map: HashMap<i32, i32> = HashMap::new();
+        let mut resized: bool = false;
+
+        for key in 0..1000 {
+            assert_eq!(map.insert(key, key * 2, GFP_KERNEL), Ok(None));
+            resized |= map.is_resizing();
+
+            // Entries are found wherever they are during a resize.
+            for previous in [0, key / 2, key] {
+                assert_eq!(map.get(&previous), Some(&(previous * 2)));
+            }
+        }
+        assert!(resized);
+        assert!(map.buckets() * 3 / 4 >= map.len());
+
+        let mut count: usize = 0;
+        let mut sum: i64 = 0;
+        () = map.for_each(|key: &i32, value: &i32| {
+            assert_eq!(*value, key * 2);
+            count += 1;
+            sum += i64::from(*key);
+        });
+        assert_eq!(count, 1000);
+        assert_eq!(sum, 999 * 1000 / 2);
+
+        // Removals finish the resize.
+        for key in 0..1000 {
+            assert_eq!(map.remove(&key), Some(key * 2));
+        }
+        assert!(!map.is_resizing());
+        assert_eq!(map.len(), 0);
+    }
+
+    #[test]
+    fn test_alloc_failure() {
+        let mut map: HashMap<i32, i32> = HashMap::new();
+        assert_eq!(map.insert(1, 1, GFP_KERNEL), Ok(None));
+        let buckets: usize = map.buckets();
+
+        // A table of 2^59 buckets, which can be described but not allocated,
+        // so the allocator fails. The map must be left as it was.
+        let ret: Result = map.try_reserve(usize::MAX / 64, GFP_KERNEL | __GFP_NOWARN);
+        assert_eq!(ret, Err(ENOMEM));
+        // Too large to even compute the number of buckets.
+        let ret: Result = map.try_reserve(usize::MAX, GFP_KERNEL | __GFP_NOWARN);
+        assert_eq!(ret, Err(ENOMEM));
+
+        assert_eq!(map.buckets(), buckets);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&1), Some(&1));
+        assert_eq!(map.insert(2, 2, GFP_KERNEL), Ok(None));
+    }
+
+    #[test]
+    fn test_insert_failure() {
+        let mut map: HashMap<i32, i32, BuildFnv1a, Failing> = HashMap::new();
+        for key in 0..4 {
+            assert_eq!(map.insert(key, key, GFP_KERNEL), Ok(None));
+        }
+        let buckets: usize = map.buckets();
+
+        FAIL.store(true, Ordering::Relaxed);
+        let failed: Result<Option<i32>> = map.insert(4, 4, GFP_KERNEL);
+        // Replacing a value allocates nothing.
+        let replaced: Result<Option<i32>> = map.insert(0, 10, GFP_KERNEL);
+        FAIL.store(false, Ordering::Relaxed);
+
+        assert_eq!(failed, Err(ENOMEM));
+        assert_eq!(replaced, Ok(Some(0)));
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.buckets(), buckets);
+        assert_eq!(map.get(&4), None);
+        assert_eq!(map.get(&0), Some(&10));
+        for key in 1..4 {
+            assert_eq!(map.get(&key), Some(&key));
+        }
+        assert_eq!(map.insert(4, 4, GFP_KERNEL), Ok(None));
+    }
+
+    #[test]
+    fn test_long_chain_drop() {
+        // Far more nodes than the kernel stack could hold frames for, were
+        // the chain dropped recursively.
+        let mut map: HashMap<i32, i32, BuildHasherDefault<Constant>> =
+            HashMap::with_hasher(BuildHasherDefault::default());
+        for key in 0..10_000 {
+            assert_eq!(map.insert(key, key, GFP_KERNEL), Ok(None));
+        }
+        drop(map);
+    }
+
+    #[test]
+    fn test_heap_order() {
+        let mut queue: PriorityQueue<u32, u32> = PriorityQueue::new();
+
+        for priority in [5, 3, 8, 1, 9, 2, 7, 3] {
+            assert!(queue.push(priority, priority * 10, GFP_KERNEL).is_ok());
+        }
+        assert_eq!(queue.len(), 8);
+        assert_eq!(queue.peek(), Some((&1, &10)));
+
+        let mut previous: u32 = 0;
+        while let Some((priority, item)) = queue.pop() {
+            assert!(priority >= previous);
+            assert_eq!(item, priority * 10);
+            previous = priority;
+        }
+        assert_eq!(queue.len(), 0);
+        assert_eq!(queue.peek(), None);
+    }
+
+    #[test]
+    fn test_heap_decrease_key() -> Result {
+        let mut queue: PriorityQueue<u32, &str> = PriorityQueue::new();
+
+        let a: Handle = queue.push(10, "a", GFP_KERNEL)?;
+        let b: Handle = queue.push(20, "b", GFP_KERNEL)?;
+        let c: Handle = queue.push(30, "c", GFP_KERNEL)?;
+
+        assert_eq!(queue.decrease_key(c, 5), Ok(()));
+        assert_eq!(queue.decrease_key(b, 25), Err(EINVAL));
+        assert_eq!(queue.pop(), Some((5, "c")));
+
+        // `c` is gone, and its slot is reused by `d` with a new generation.
+        assert_eq!(queue.decrease_key(c, 1), Err(ENOENT));
+        let d: Handle = queue.push(15, "d", GFP_KERNEL)?;
+        assert!(c != d);
+        assert_eq!(queue.decrease_key(c, 1), Err(ENOENT));
+
+        assert_eq!(queue.decrease_key(d, 1), Ok(()));
+        assert_eq!(queue.pop(), Some((1, "d")));
+        assert_eq!(queue.pop(), Some((10, "a")));
+        assert_eq!(queue.decrease_key(a, 0), Err(ENOENT));
+        assert_eq!(queue.pop(), Some((20, "b")));
+        assert_eq!(queue.pop(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Chip variants supported by the Rust Platform driver sample.
+
+use core::fmt;
+
+/// Revision of the chip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Revision {
+    V1,
+    V2,
+}
+
+/// Optional features of a chip.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Features(u32);
+
+impl Features {
+    /// The chip raises an interrupt.
+    pub(crate) const IRQ: Features = Features(1 << 0);
+    /// The chip has a low-power mode.
+    pub(crate) const LOW_POWER: Features = Features(1 << 1);
+    /// The chip has per-channel threshold registers.
+    pub(crate) const THRESHOLDS: Features = Features(1 << 2);
+
+    const NAMES: [(Features, &'static str); 3] = [
+        (Features::IRQ, "irq"),
+        (Features::LOW_POWER, "low-power"),
+        (Features::THRESHOLDS, "thresholds"),
+    ];
+
+    const fn union(self, other: Features) -> Features {
+        Features(self.0 | other.0)
+    }
+
+    /// Raw bit mask of the features.
+    pub(crate) fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether all features of `other` are present.
+    pub(crate) fn contains(self, other: Features) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl fmt::Display for Features {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first: bool = true;
+        for (feature, name) in Features::NAMES {
+            if self.contains(feature) {
+                () = f.write_str(if first { "" } else { "," })?;
+                () = f.write_str(name)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Features {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{self}]")
+    }
+}
+
+/// Static description of a chip variant, selected by the match table entry.
+#[derive(Debug)]
+pub(crate) struct Chip {
+    pub(crate) revision: Revision,
+    pub(crate) features: Features,
+    /// Largest supported `test,channels`.
+    pub(crate) max_channels: u32,
+}
+
+/// First revision: four channels, thresholds, no interrupt.
+pub(crate) static V1: Chip = Chip {
+    revision: Revision::V1,
+    features: Features::THRESHOLDS,
+    max_channels: 4,
+};
+
+/// Second revision: eight channels, thresholds, interrupt and low-power
+/// mode.
+pub(crate) static V2: Chip = Chip {
+    revision: Revision::V2,
+    features: Features::IRQ
+        .union(Features::LOW_POWER)
+        .union(Features::THRESHOLDS),
+    max_channels: 8,
+};
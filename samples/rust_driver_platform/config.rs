@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Firmware properties of the Rust Platform driver sample.
+
+use kernel::{
+    alloc::kvec::KVec,
+    c_str, dev_err,
+    device::{Device, property::FwNode},
+    error::{
+        Result,
+        code::{EINVAL, ENOENT},
+    },
+    str::{CStr, CString},
+};
+
+const CHANNELS: &CStr = c_str!("test,channels");
+const THRESHOLDS: &CStr = c_str!("test,thresholds");
+const LABEL: &CStr = c_str!("label");
+const LOW_POWER: &CStr = c_str!("test,low-power");
+
+/// Configuration read from the firmware node of the device.
+#[derive(Debug)]
+pub(crate) struct Config {
+    /// Number of channels, from the required `test,channels` property.
+    pub(crate) channels: u32,
+    /// Per-channel thresholds, from the optional `test,thresholds` array.
+    pub(crate) thresholds: KVec<u32>,
+    /// Name of the device, from the optional `label` property.
+    pub(crate) label: CString,
+    /// Whether the optional `test,low-power` flag is present.
+    pub(crate) low_power: bool,
+}
+
+impl Config {
+    /// Reads the configuration of `dev`, logging why probe fails if a required
+    /// property is missing or any property is malformed.
+    pub(crate) fn parse(dev: &Device) -> Result<Self> {
+        let Some(fwnode) = dev.fwnode() else {
+            dev_err!(dev, "Missing firmware node.\n");
+            return Err(ENOENT);
+        };
+
+        let channels: u32 = fwnode.property_read(CHANNELS).required_by(dev)?;
+        // The upper bound depends on the chip, see `SampleDriver::check`.
+        if channels == 0 {
+            dev_err!(dev, "'{CHANNELS}' must be at least 1.\n");
+            return Err(EINVAL);
+        }
+
+        let thresholds: KVec<u32> = Self::thresholds(dev, fwnode, channels)?;
+
+        // Optional properties that are present must still be well-formed, so
+        // presence is checked first and the read is then required.
+        let label: CString = if fwnode.property_present(LABEL) {
+            fwnode.property_read::<CString>(LABEL).required_by(dev)?
+        } else {
+            c_str!("rust-device").to_cstring()?
+        };
+
+        let low_power: bool = fwnode.property_read_bool(LOW_POWER);
+
+        Ok(Config {
+            channels,
+            thresholds,
+            label,
+            low_power,
+        })
+    }
+
+    fn thresholds(dev: &Device, fwnode: &FwNode, channels: u32) -> Result<KVec<u32>> {
+        if !fwnode.property_present(THRESHOLDS) {
+            return Ok(KVec::new());
+        }
+
+        let len: usize = match fwnode.property_count_elem::<u32>(THRESHOLDS) {
+            Ok(len) if len <= channels as usize => len,
+            Ok(len) => {
+                dev_err!(
+                    dev,
+                    "'{THRESHOLDS}' has {len} entries for {channels} channels.\n"
+                );
+                return Err(EINVAL);
+            }
+            Err(e) => {
+                dev_err!(dev, "'{THRESHOLDS}' is not an array of u32.\n");
+                return Err(e);
+            }
+        };
+
+        fwnode
+            .property_read_array_vec::<u32>(THRESHOLDS, len)?
+            .required_by(dev)
+    }
+}
@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Firmware blob of the Rust Platform driver sample.
+//!
+//! The blob provides default channel thresholds, used when the firmware node
+//! has no `test,thresholds`. Its name comes from the `firmware-name` property
+//! and defaults to `rust_driver_platform.bin`. `mkfirmware` writes one, which
+//! can be dropped into `/lib/firmware` inside a VM:
+//!
+//! ```text
+//! ./mkfirmware /lib/firmware/rust_driver_platform.bin 10 20 30 40
+//! ```
+//!
+//! All fields are little-endian `u32`s:
+//!
+//! | Offset | Field                                                      |
+//! |--------|------------------------------------------------------------|
+//! | 0      | Magic, `RDPF`                                              |
+//! | 4      | Version, 1                                                 |
+//! | 8      | Number of thresholds, at most 8                            |
+//! | 12     | Checksum, the complement of the wrapping sum of thresholds |
+//! | 16     | Thresholds                                                 |
+
+use kernel::{
+    alloc::{flags::GFP_KERNEL, kvec::KVec},
+    c_str, dev_err, dev_info, dev_warn,
+    device::Device,
+    error::{Error, Result, code::EINVAL},
+    firmware::Firmware,
+    str::{CStr, CString},
+};
+
+const FIRMWARE_NAME: &CStr = c_str!("firmware-name");
+
+/// Blob requested when the firmware node has no `firmware-name`.
+const DEFAULT_NAME: &CStr = c_str!("rust_driver_platform.bin");
+
+const MAGIC: &[u8; 4] = b"RDPF";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 16;
+const MAX_THRESHOLDS: usize = 8;
+
+/// Threshold of every channel when there is no blob.
+const DEFAULT_THRESHOLD: u32 = 100;
+
+/// Returns the default thresholds of `channels` channels, from the blob if
+/// there is one and built-in ones otherwise.
+pub(crate) fn thresholds(dev: &Device, channels: u32) -> Result<KVec<u32>> {
+    let name: CString = match dev.fwnode() {
+        Some(fwnode) if fwnode.property_present(FIRMWARE_NAME) => fwnode
+            .property_read::<CString>(FIRMWARE_NAME)
+            .required_by(dev)?,
+        _ => DEFAULT_NAME.to_cstring()?,
+    };
+
+    let mut thresholds: KVec<u32> = match Firmware::request_nowarn(&name, dev) {
+        Ok(fw) => {
+            let thresholds: KVec<u32> = parse(fw.data()).inspect_err(|_: &Error| {
+                dev_err!(dev, "Invalid firmware '{name}'.\n");
+            })?;
+            dev_info!(
+                dev,
+                "Loaded {} threshold(s) from '{name}'.\n",
+                thresholds.len()
+            );
+            thresholds
+        }
+        Err(_) => {
+            dev_warn!(dev, "No firmware '{name}', using built-in thresholds.\n");
+            KVec::new()
+        }
+    };
+
+    // Channels the blob does not cover get the built-in threshold.
+    thresholds.truncate(channels as usize);
+    while thresholds.len() < channels as usize {
+        () = thresholds.push(DEFAULT_THRESHOLD, GFP_KERNEL)?;
+    }
+
+    Ok(thresholds)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    let mut bytes: [u8; 4] = [0; 4];
+    () = bytes.copy_from_slice(&data[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+/// Checks the header and checksum of `data` and returns its thresholds.
+fn parse(data: &[u8]) -> Result<KVec<u32>> {
+    if data.len() < HEADER_LEN || &data[..4] != MAGIC || read_u32(data, 4) != VERSION {
+        return Err(EINVAL);
+    }
+
+    let count: usize = read_u32(data, 8) as usize;
+    if count > MAX_THRESHOLDS || data.len() != HEADER_LEN + count * 4 {
+        return Err(EINVAL);
+    }
+
+    let mut thresholds: KVec<u32> = KVec::with_capacity(count, GFP_KERNEL)?;
+    let mut sum: u32 = 0;
+    for index in 0..count {
+        let threshold: u32 = read_u32(data, HEADER_LEN + index * 4);
+        sum = sum.wrapping_add(threshold);
+        () = thresholds.push(threshold, GFP_KERNEL)?;
+    }
+
+    if read_u32(data, 12) != !sum {
+        return Err(EINVAL);
+    }
+
+    Ok(thresholds)
+}
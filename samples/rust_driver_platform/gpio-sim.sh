@@ -0,0 +1,51 @@
+#!/bin/sh
+# SPDX-License-Identifier: GPL-2.0
+#
+# Connects rust_driver_platform.0 to a gpio-sim chip, toggles its `event`
+# input and checks the `gpio` attribute. Run as root from the directory
+# holding the built modules, with configfs mounted and gpio-sim available.
+
+set -e
+
+SIM=/sys/kernel/config/gpio-sim/rust-sim
+DEV=/sys/bus/platform/devices/rust_driver_platform.0
+
+modprobe gpio-sim
+mkdir "$SIM" "$SIM/bank0"
+echo rust-sim > "$SIM/bank0/label"
+echo 2 > "$SIM/bank0/num_lines"
+echo 1 > "$SIM/live"
+
+# gpio-sim names its platform device after the configfs entry.
+LINES=$(echo /sys/devices/platform/$(cat "$SIM/dev_name")/gpiochip*/)
+
+insmod rust_driver_platform.ko
+insmod rust_driver_platform_device.ko gpio_sim=1
+
+if [ "$(cat "$LINES/sim_gpio0/value")" != "1" ]; then
+	echo "enable line was not driven" >&2
+	exit 1
+fi
+
+echo pull-up > "$LINES/sim_gpio1/pull"
+echo pull-down > "$LINES/sim_gpio1/pull"
+sleep 1
+cat "$DEV/gpio"
+if ! grep -q "edges=2" "$DEV/gpio"; then
+	echo "edges were not counted" >&2
+	exit 1
+fi
+
+echo 0 > "$DEV/gpio"
+if [ "$(cat "$LINES/sim_gpio0/value")" != "0" ]; then
+	echo "enable line was not cleared" >&2
+	exit 1
+fi
+
+rmmod rust_driver_platform_device
+rmmod rust_driver_platform
+
+echo 0 > "$SIM/live"
+rmdir "$SIM/bank0" "$SIM"
+
+echo "ok"
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! GPIO lines of the Rust Platform driver sample.
+//!
+//! The lines are named `enable`, an output driven by the driver, and `event`,
+//! an input whose edges raise an interrupt. In a device tree:
+//!
+//! ```text
+//! enable-gpios = <&gpio 0 GPIO_ACTIVE_HIGH>;
+//! event-gpios = <&gpio 1 GPIO_ACTIVE_HIGH>;
+//! ```
+//!
+//! `gpio-sim.sh` connects them to a `gpio-sim` chip instead. The lines are
+//! acquired in `rust_driver_platform_gpio.c`, as there are no Rust GPIO
+//! consumer abstractions yet.
+
+use {
+    core::ptr::NonNull,
+    kernel::{
+        bindings,
+        device::{Core, Device},
+        error::{Result, from_err_ptr, to_result},
+        ffi::{c_int, c_uint},
+    },
+};
+
+/// Opaque `struct rust_driver_platform_gpio`.
+#[repr(C)]
+struct RawGpio {
+    _private: [u8; 0],
+}
+
+// Declared in `rust_driver_platform.h`.
+extern "C" {
+    fn rust_driver_platform_gpio_get(dev: *mut bindings::device) -> *mut RawGpio;
+    fn rust_driver_platform_gpio_set_enable(gpio: *mut RawGpio, value: c_int) -> c_int;
+    fn rust_driver_platform_gpio_enable(gpio: *mut RawGpio) -> c_int;
+    fn rust_driver_platform_gpio_level(gpio: *mut RawGpio) -> c_int;
+    fn rust_driver_platform_gpio_edges(gpio: *mut RawGpio) -> c_uint;
+}
+
+/// The `enable` and `event` lines of a device.
+///
+/// The lines, their interrupt and the state behind the pointer are
+/// device-managed, and the driver data holding a `Gpio` is dropped before
+/// devres releases them on unbind.
+pub(crate) struct Gpio(NonNull<RawGpio>);
+
+// SAFETY: The C side only uses sleeping GPIO accessors and atomics, which may
+// be called from any thread.
+unsafe impl Send for Gpio {}
+
+// SAFETY: As above, and none of the accessors need exclusive access.
+unsafe impl Sync for Gpio {}
+
+impl Gpio {
+    /// Acquires the lines of `dev` until it is unbound, if it has any.
+    pub(crate) fn get(dev: &Device<Core>) -> Result<Option<Self>> {
+        // SAFETY: `dev` is valid.
+        let gpio: *mut RawGpio =
+            from_err_ptr(unsafe { rust_driver_platform_gpio_get(dev.as_raw()) })?;
+        Ok(NonNull::new(gpio).map(Gpio))
+    }
+
+    /// Drives the `enable` line.
+    pub(crate) fn set_enable(&self, value: bool) -> Result {
+        // SAFETY: `self.0` is valid while the device is bound.
+        to_result(unsafe {
+            rust_driver_platform_gpio_set_enable(self.0.as_ptr(), c_int::from(value))
+        })
+    }
+
+    /// Reads back the `enable` line.
+    pub(crate) fn enable(&self) -> Result<bool> {
+        // SAFETY: `self.0` is valid while the device is bound.
+        let value: c_int = unsafe { rust_driver_platform_gpio_enable(self.0.as_ptr()) };
+        () = to_result(value)?;
+        Ok(value != 0)
+    }
+
+    /// Level of the `event` line after its last edge.
+    pub(crate) fn level(&self) -> bool {
+        // SAFETY: `self.0` is valid while the device is bound.
+        unsafe { rust_driver_platform_gpio_level(self.0.as_ptr()) != 0 }
+    }
+
+    /// Number of edges seen on the `event` line.
+    pub(crate) fn edges(&self) -> u32 {
+        // SAFETY: `self.0` is valid while the device is bound.
+        unsafe { rust_driver_platform_gpio_edges(self.0.as_ptr()) }
+    }
+}
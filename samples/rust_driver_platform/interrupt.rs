@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Interrupt handling of the Rust Platform driver sample.
+//!
+//! The interrupt is optional. Devices registered by
+//! `rust_driver_platform_device` have a simulated one, which can be raised
+//! from debugfs.
+
+use {
+    core::{
+        pin::Pin,
+        sync::atomic::{AtomicU64, Ordering},
+    },
+    kernel::{
+        alloc::{flags::GFP_KERNEL, kbox::KBox},
+        c_str, dev_dbg, dev_info,
+        device::{Bound, Core, Device},
+        error::{Result, code::ENXIO},
+        irq::{self, IrqReturn, ThreadedHandler, ThreadedIrqReturn, ThreadedRegistration},
+        platform,
+    },
+};
+
+/// Counts the interrupts of a device.
+pub(crate) struct Handler {
+    /// Interrupts seen by the hardirq handler.
+    raised: AtomicU64,
+    /// Interrupts completed by the threaded handler.
+    handled: AtomicU64,
+}
+
+impl Handler {
+    /// Number of interrupts seen and handled so far.
+    pub(crate) fn counts(&self) -> (u64, u64) {
+        (
+            self.raised.load(Ordering::Relaxed),
+            self.handled.load(Ordering::Relaxed),
+        )
+    }
+}
+
+impl ThreadedHandler for Handler {
+    fn handle(&self, dev: &Device<Bound>) -> ThreadedIrqReturn {
+        let raised: u64 = self.raised.fetch_add(1, Ordering::Relaxed) + 1;
+        dev_dbg!(dev, "Interrupt {raised} raised.\n");
+        ThreadedIrqReturn::WakeThread
+    }
+
+    fn handle_threaded(&self, dev: &Device<Bound>) -> IrqReturn {
+        let handled: u64 = self.handled.fetch_add(1, Ordering::Relaxed) + 1;
+        dev_info!(dev, "Interrupt {handled} handled.\n");
+        IrqReturn::Handled
+    }
+}
+
+/// Registration of the interrupt handler of a device.
+pub(crate) type Registration = Pin<KBox<ThreadedRegistration<Handler>>>;
+
+/// Requests the first interrupt of `pdev`, if it has one.
+pub(crate) fn request(pdev: &platform::Device<Core>) -> Result<Option<Registration>> {
+    let handler: Handler = Handler {
+        raised: AtomicU64::new(0),
+        handled: AtomicU64::new(0),
+    };
+
+    match pdev.request_optional_threaded_irq_by_index(
+        irq::Flags::TRIGGER_NONE,
+        0,
+        c_str!("rust_driver_platform"),
+        handler,
+    ) {
+        Ok(init) => Ok(Some(KBox::pin_init(init, GFP_KERNEL)?)),
+        Err(e) if e == ENXIO => Ok(None),
+        Err(e) => Err(e),
+    }
+}
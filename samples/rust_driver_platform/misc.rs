@@ -0,0 +1,215 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Per-instance misc devices of the Rust Platform driver sample.
+//!
+//! Every bound device gets a `/dev/rust-platform-N` node whose ioctls return
+//! the chip variant and configuration of the device. Both are copied when the
+//! node is opened, so open files keep working after the device is unbound.
+//!
+//! `N` is the lowest free slot at probe time, not the platform device id:
+//! devices probed out of order or rebound get whichever slot is free. There
+//! are eight slots, and probe fails with `ENOSPC` once all are taken.
+
+use {
+    crate::{SampleDriver, chip},
+    core::{
+        pin::Pin,
+        sync::atomic::{AtomicU32, Ordering},
+    },
+    kernel::{
+        alloc::{flags::GFP_KERNEL, kbox::KBox},
+        c_str, container_of, dev_dbg, dev_err,
+        device::Device,
+        error::{
+            Result,
+            code::{ENOSPC, ENOTTY},
+        },
+        fs::File,
+        ioctl::{_IOC_SIZE, _IOR},
+        miscdevice::{MiscDevice, MiscDeviceOptions, MiscDeviceRegistration},
+        prelude::vtable,
+        str::CStr,
+        transmute::AsBytes,
+        types::ARef,
+        uaccess::{UserSlice, UserSliceWriter},
+    },
+};
+
+/// Returns a [`InfoAbi`] describing the chip.
+const RUST_PLATFORM_GET_INFO: u32 = _IOR::<InfoAbi>('P' as u32, 0x01);
+/// Returns a [`ConfigAbi`] holding the configuration.
+const RUST_PLATFORM_GET_CONFIG: u32 = _IOR::<ConfigAbi>('P' as u32, 0x02);
+
+/// Length of [`ConfigAbi::label`], including the terminating NUL.
+const LABEL_LEN: usize = 32;
+
+/// Names of the misc devices, which must be static.
+static NAMES: [&CStr; 8] = [
+    c_str!("rust-platform-0"),
+    c_str!("rust-platform-1"),
+    c_str!("rust-platform-2"),
+    c_str!("rust-platform-3"),
+    c_str!("rust-platform-4"),
+    c_str!("rust-platform-5"),
+    c_str!("rust-platform-6"),
+    c_str!("rust-platform-7"),
+];
+
+/// Bitmap of the indices into [`NAMES`] in use.
+static USED: AtomicU32 = AtomicU32::new(0);
+
+/// Chip variant, as returned by `RUST_PLATFORM_GET_INFO`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InfoAbi {
+    /// Chip revision, starting at 1.
+    revision: u32,
+    /// Bit 0: interrupt, bit 1: low-power mode, bit 2: thresholds.
+    features: u32,
+    max_channels: u32,
+    /// 0: bound by name, 1: device tree, 2: ACPI.
+    firmware: u32,
+}
+
+// SAFETY: `InfoAbi` only has `u32` fields and no padding.
+unsafe impl AsBytes for InfoAbi {}
+
+/// Configuration, as returned by `RUST_PLATFORM_GET_CONFIG`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ConfigAbi {
+    channels: u32,
+    low_power: u32,
+    /// Number of valid entries in `thresholds`.
+    nr_thresholds: u32,
+    thresholds: [u32; 8],
+    /// NUL-terminated, truncated if needed.
+    label: [u8; LABEL_LEN],
+}
+
+// SAFETY: `ConfigAbi` only has integer fields and arrays thereof, and no
+// padding.
+unsafe impl AsBytes for ConfigAbi {}
+
+/// An index into [`NAMES`], released on drop.
+pub(crate) struct Minor(usize);
+
+impl Minor {
+    /// Number of slots.
+    pub(crate) const MAX: usize = NAMES.len();
+
+    pub(crate) fn alloc() -> Result<Self> {
+        let mut used: u32 = USED.load(Ordering::Relaxed);
+        loop {
+            let index: usize = used.trailing_ones() as usize;
+            if index >= NAMES.len() {
+                return Err(ENOSPC);
+            }
+
+            match USED.compare_exchange(
+                used,
+                used | 1 << index,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Ok(Minor(index)),
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    pub(crate) fn options(&self) -> MiscDeviceOptions {
+        MiscDeviceOptions {
+            name: NAMES[self.0],
+        }
+    }
+}
+
+impl Drop for Minor {
+    fn drop(&mut self) {
+        let _: u32 = USED.fetch_and(!(1 << self.0), Ordering::Relaxed);
+    }
+}
+
+/// An open misc device node.
+pub(crate) struct Instance {
+    dev: ARef<Device>,
+    info: InfoAbi,
+    config: ConfigAbi,
+}
+
+impl Instance {
+    fn new(this: &SampleDriver) -> Self {
+        let dev: &Device = this.pdev.as_ref();
+        let chip: &chip::Chip = this.chip;
+        let config: &crate::config::Config = &this.config;
+
+        let mut abi: ConfigAbi = ConfigAbi {
+            channels: config.channels,
+            low_power: u32::from(config.low_power),
+            nr_thresholds: config.thresholds.len() as u32,
+            thresholds: [0; 8],
+            label: [0; LABEL_LEN],
+        };
+        () = abi.thresholds[..config.thresholds.len()].copy_from_slice(&config.thresholds);
+        let label: &[u8] = config.label.as_bytes();
+        let len: usize = label.len().min(LABEL_LEN - 1);
+        () = abi.label[..len].copy_from_slice(&label[..len]);
+
+        Instance {
+            dev: ARef::from(dev),
+            info: InfoAbi {
+                revision: match chip.revision {
+                    chip::Revision::V1 => 1,
+                    chip::Revision::V2 => 2,
+                },
+                features: chip.features.bits(),
+                max_channels: chip.max_channels,
+                firmware: match &this.info {
+                    None => 0,
+                    Some(info) => match info.firmware {
+                        crate::Firmware::Of => 1,
+                        crate::Firmware::Acpi => 2,
+                    },
+                },
+            },
+            config: abi,
+        }
+    }
+}
+
+#[vtable]
+impl MiscDevice for Instance {
+    type Ptr = Pin<KBox<Self>>;
+
+    fn open(_file: &File, misc: &MiscDeviceRegistration<Self>) -> Result<Pin<KBox<Self>>> {
+        // SAFETY: `Instance` is only registered as the `misc` field of a
+        // `SampleDriver`, and the registration is the first field to be
+        // dropped. misc_deregister() waits for running opens, so the whole
+        // driver is alive here.
+        let this: &SampleDriver =
+            unsafe { &*container_of!(core::ptr::from_ref(misc).cast_mut(), SampleDriver, misc) };
+
+        dev_dbg!(this.pdev.as_ref(), "Opened misc device.\n");
+        Ok(KBox::pin(Instance::new(this), GFP_KERNEL)?)
+    }
+
+    fn ioctl(me: Pin<&Instance>, _file: &File, cmd: u32, arg: usize) -> Result<isize> {
+        let mut writer: UserSliceWriter = UserSlice::new(arg, _IOC_SIZE(cmd)).writer();
+
+        match cmd {
+            RUST_PLATFORM_GET_INFO => {
+                () = writer.write::<InfoAbi>(&me.info)?;
+            }
+            RUST_PLATFORM_GET_CONFIG => {
+                () = writer.write::<ConfigAbi>(&me.config)?;
+            }
+            _ => {
+                dev_err!(me.dev, "IOCTL not recognised: {cmd}\n");
+                return Err(ENOTTY);
+            }
+        }
+
+        Ok(0)
+    }
+}
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Writes a firmware blob for the Rust Platform driver sample.
+//!
+//! Usage: `mkfirmware <path> <threshold>...`, see `firmware.rs` for the
+//! format.
+
+use std::{env, fs, num::ParseIntError, process};
+
+const MAX_THRESHOLDS: usize = 8;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() - 2 > MAX_THRESHOLDS {
+        eprintln!(
+            "Usage: {} <path> <threshold>... (at most {MAX_THRESHOLDS})",
+            args[0]
+        );
+        process::exit(1);
+    }
+
+    let thresholds: Vec<u32> = args[2..]
+        .iter()
+        .map(|arg: &String| -> u32 {
+            arg.parse::<u32>()
+                .unwrap_or_else(|_: ParseIntError| -> u32 {
+                    eprintln!("Invalid threshold '{arg}'.");
+                    process::exit(1);
+                })
+        })
+        .collect();
+
+    let sum: u32 = thresholds
+        .iter()
+        .fold(0, |sum: u32, threshold: &u32| -> u32 {
+            sum.wrapping_add(*threshold)
+        });
+
+    let mut blob: Vec<u8> = b"RDPF".to_vec();
+    for word in [1, thresholds.len() as u32, !sum].iter().chain(&thresholds) {
+        () = blob.extend_from_slice(&word.to_le_bytes());
+    }
+
+    if let Err(e) = fs::write(&args[1], blob) {
+        eprintln!("Cannot write '{}': {e}.", args[1]);
+        process::exit(1);
+    }
+}
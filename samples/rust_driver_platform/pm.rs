@@ -0,0 +1,256 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Power management of the Rust Platform driver sample.
+//!
+//! `platform::Driver` has no power management callbacks yet, so the driver
+//! installs its own PM domain on devices that do not already belong to one.
+//! Its callbacks save the register state and disable the device on suspend,
+//! and restore it on resume. Runtime suspend happens after an autosuspend
+//! delay of one second without register accesses.
+//!
+//! Requires `CONFIG_PM`. Inside a VM:
+//!
+//! ```text
+//! cat /sys/bus/platform/devices/rust_driver_platform.0/power/runtime_status
+//! echo on > /sys/bus/platform/devices/rust_driver_platform.0/power/control
+//! echo auto > /sys/bus/platform/devices/rust_driver_platform.0/power/control
+//! echo freeze > /sys/power/state  # or: rtcwake -m freeze -s 5
+//! ```
+//!
+//! System transitions are logged with `dev_info!`, runtime ones with
+//! `dev_dbg!`.
+
+use {
+    crate::{SampleDriver, regs, util::Table},
+    core::sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    kernel::{
+        bindings, dev_dbg, dev_info,
+        device::{self, Core},
+        error::{Result, code::EBUSY, to_result},
+        ffi::c_int,
+    },
+};
+
+/// Autosuspend delay, in milliseconds.
+const AUTOSUSPEND_DELAY_MS: c_int = 1000;
+
+static DOMAIN: Table<bindings::dev_pm_domain> = Table(bindings::dev_pm_domain {
+    ops: bindings::dev_pm_ops {
+        suspend: Some(suspend),
+        resume: Some(resume),
+        freeze: Some(suspend),
+        thaw: Some(resume),
+        poweroff: Some(suspend),
+        restore: Some(resume),
+        runtime_suspend: Some(runtime_suspend),
+        runtime_resume: Some(runtime_resume),
+        // SAFETY: All other callbacks are optional.
+        ..unsafe { core::mem::zeroed() }
+    },
+    // SAFETY: All other fields of `dev_pm_domain` are optional.
+    ..unsafe { core::mem::zeroed() }
+});
+
+/// Power management state of a device.
+pub(crate) struct Pm {
+    /// Whether runtime PM was enabled through [`DOMAIN`].
+    enabled: AtomicBool,
+    /// `CTRL` and the channel thresholds, saved while suspended.
+    saved: [AtomicU32; 1 + regs::NR_THRESHOLDS],
+}
+
+impl Pm {
+    pub(crate) fn new() -> Self {
+        Pm {
+            enabled: AtomicBool::new(false),
+            saved: [const { AtomicU32::new(0) }; 1 + regs::NR_THRESHOLDS],
+        }
+    }
+
+    /// Installs [`DOMAIN`] on `dev` and enables runtime PM, unless `dev`
+    /// already has a PM domain, e.g. from ACPI.
+    pub(crate) fn enable(&self, dev: &device::Device<Core>) -> Result {
+        let raw: *mut bindings::device = dev.as_raw();
+
+        // SAFETY: `raw` is valid, and the device lock is held during probe.
+        if unsafe { !(*raw).pm_domain.is_null() } {
+            dev_info!(
+                dev,
+                "Already in a PM domain, leaving power management to it.\n"
+            );
+            return Ok(());
+        }
+
+        // SAFETY: As above, and the device is not bound yet as required by
+        // `dev_pm_domain_set`. `DOMAIN` is static.
+        unsafe {
+            () = bindings::dev_pm_domain_set(raw, (&raw const DOMAIN.0).cast_mut());
+            () = bindings::pm_runtime_set_autosuspend_delay(raw, AUTOSUSPEND_DELAY_MS);
+            () = bindings::__pm_runtime_use_autosuspend(raw, true);
+        }
+
+        // SAFETY: `raw` is valid and runtime PM is still disabled.
+        let active: Result = to_result(unsafe {
+            bindings::__pm_runtime_set_status(raw, bindings::RPM_ACTIVE as u32)
+        });
+        if let Err(e) = active {
+            // SAFETY: Undoes the calls above.
+            unsafe {
+                () = bindings::__pm_runtime_use_autosuspend(raw, false);
+                () = bindings::dev_pm_domain_set(raw, core::ptr::null_mut());
+            }
+            return Err(e);
+        }
+
+        // SAFETY: `raw` is valid.
+        () = unsafe { bindings::pm_runtime_enable(raw) };
+        self.enabled.store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Undoes [`Pm::enable`], waiting for running callbacks.
+    pub(crate) fn disable(&self, dev: &device::Device) {
+        if !self.enabled.swap(false, Ordering::Relaxed) {
+            return;
+        }
+
+        let raw: *mut bindings::device = dev.as_raw();
+
+        // SAFETY: Runtime PM was enabled and `DOMAIN` installed by
+        // `Pm::enable`, and the device lock is held during probe and unbind.
+        unsafe {
+            () = bindings::__pm_runtime_disable(raw, false);
+            () = bindings::__pm_runtime_use_autosuspend(raw, false);
+            let _ = bindings::__pm_runtime_set_status(raw, bindings::RPM_SUSPENDED as u32);
+            () = bindings::dev_pm_domain_set(raw, core::ptr::null_mut());
+        }
+    }
+
+    /// Resumes `dev` if needed and keeps it active until the returned guard
+    /// is dropped.
+    pub(crate) fn get<'a>(&self, dev: &'a device::Device) -> Result<Active<'a>> {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return Ok(Active(None));
+        }
+
+        let active: Active<'a> = Active(Some(dev));
+
+        // SAFETY: `dev` is valid. On failure, dropping `active` drops the
+        // usage count taken here.
+        () = to_result(unsafe {
+            bindings::__pm_runtime_resume(dev.as_raw(), bindings::RPM_GET_PUT as c_int)
+        })?;
+
+        Ok(active)
+    }
+
+    fn save(&self, this: &SampleDriver) -> Result {
+        () = self.saved[0].store(this.regs.read(regs::CTRL)?, Ordering::Relaxed);
+        for (channel, saved) in self.saved[1..].iter().enumerate() {
+            () = saved.store(
+                this.regs.read(regs::threshold(channel)?)?,
+                Ordering::Relaxed,
+            );
+        }
+        this.regs.set(regs::CTRL_ENABLE, 0)
+    }
+
+    fn restore(&self, this: &SampleDriver) -> Result {
+        for (channel, saved) in self.saved[1..].iter().enumerate() {
+            () = this
+                .regs
+                .write(regs::threshold(channel)?, saved.load(Ordering::Relaxed))?;
+        }
+        this.regs
+            .write(regs::CTRL, self.saved[0].load(Ordering::Relaxed))
+    }
+}
+
+/// Keeps a device runtime active, see [`Pm::get`].
+pub(crate) struct Active<'a>(Option<&'a device::Device>);
+
+impl Drop for Active<'_> {
+    fn drop(&mut self) {
+        let Some(dev) = self.0 else {
+            return;
+        };
+        let raw: *mut bindings::device = dev.as_raw();
+
+        // SAFETY: `raw` is valid and `Pm::get` took a usage count. Writing
+        // `last_busy` is what `pm_runtime_mark_last_busy` does.
+        unsafe {
+            () = core::ptr::write_volatile(
+                &raw mut (*raw).power.last_busy,
+                bindings::ktime_get_mono_fast_ns(),
+            );
+            let _ = bindings::__pm_runtime_suspend(
+                raw,
+                (bindings::RPM_GET_PUT | bindings::RPM_ASYNC | bindings::RPM_AUTO) as c_int,
+            );
+        }
+    }
+}
+
+/// Calls `f` with the driver bound to `dev`.
+///
+/// # Safety
+///
+/// `dev` must be a device [`DOMAIN`] was installed on.
+unsafe fn call(dev: *mut bindings::device, f: impl FnOnce(&SampleDriver) -> Result) -> c_int {
+    // SAFETY: By the safety requirements, and `Pm::disable` waits for
+    // callbacks before the driver is dropped.
+    let result: Result = match unsafe { SampleDriver::from_raw(dev) } {
+        Ok(this) => f(this),
+        // Probe has not returned yet.
+        Err(_) => Err(EBUSY),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => e.to_errno(),
+    }
+}
+
+unsafe extern "C" fn runtime_suspend(dev: *mut bindings::device) -> c_int {
+    // SAFETY: `runtime_suspend` is only part of `DOMAIN`.
+    unsafe {
+        call(dev, |this: &SampleDriver| -> Result {
+            dev_dbg!(this.pdev.as_ref(), "Runtime suspend.\n");
+            this.pm.save(this)
+        })
+    }
+}
+
+unsafe extern "C" fn runtime_resume(dev: *mut bindings::device) -> c_int {
+    // SAFETY: `runtime_resume` is only part of `DOMAIN`.
+    unsafe {
+        call(dev, |this: &SampleDriver| -> Result {
+            dev_dbg!(this.pdev.as_ref(), "Runtime resume.\n");
+            this.pm.restore(this)
+        })
+    }
+}
+
+unsafe extern "C" fn suspend(dev: *mut bindings::device) -> c_int {
+    // SAFETY: `suspend` is only part of `DOMAIN`.
+    unsafe {
+        call(dev, |this: &SampleDriver| -> Result {
+            dev_info!(this.pdev.as_ref(), "System suspend.\n");
+            // SAFETY: `dev` is valid, and `DOMAIN` provides runtime PM
+            // callbacks.
+            to_result(unsafe { bindings::pm_runtime_force_suspend(dev) })
+        })
+    }
+}
+
+unsafe extern "C" fn resume(dev: *mut bindings::device) -> c_int {
+    // SAFETY: `resume` is only part of `DOMAIN`.
+    unsafe {
+        call(dev, |this: &SampleDriver| -> Result {
+            dev_info!(this.pdev.as_ref(), "System resume.\n");
+            // SAFETY: `dev` is valid and was suspended by `suspend`.
+            to_result(unsafe { bindings::pm_runtime_force_resume(dev) })
+        })
+    }
+}
@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Register map of the Rust Platform driver sample.
+//!
+//! The registers live in the `reg` resource of the device. Devices without
+//! one, such as those registered by `rust_driver_platform_device`, get a
+//! simulated register block instead, so the register logic can be exercised
+//! without hardware.
+//!
+//! The mapping is device-managed: it is released when the device is unbound,
+//! after which accesses fail with `ENXIO`. The simulated block is plain memory
+//! owned by the driver data, so accesses to it always succeed.
+
+use {
+    core::{
+        pin::Pin,
+        sync::atomic::{AtomicU32, Ordering},
+    },
+    kernel::{
+        alloc::{flags::GFP_KERNEL, kbox::KBox},
+        device::Core,
+        devres::Devres,
+        error::{
+            Error, Result,
+            code::{EINVAL, ENXIO},
+        },
+        io::mem::IoMem,
+        new_spinlock, platform,
+        sync::{
+            SpinLock,
+            lock::{Guard, spinlock::SpinLockBackend},
+        },
+        try_pin_init,
+    },
+    pin_init::{PinInit, pin_data},
+};
+
+/// Size of the register block.
+pub(crate) const SIZE: usize = 0x30;
+
+/// Value of [`ID`] on a supported device, "RUST" in ASCII.
+pub(crate) const ID_VALUE: u32 = 0x5255_5354;
+
+/// A 32-bit register at a byte offset into the register block.
+#[derive(Clone, Copy)]
+pub(crate) struct Register(usize);
+
+/// A bit field of a [`Register`].
+#[derive(Clone, Copy)]
+pub(crate) struct Field {
+    reg: Register,
+    mask: u32,
+}
+
+impl Field {
+    const fn new(reg: Register, mask: u32) -> Self {
+        Field { reg, mask }
+    }
+
+    /// Extracts the field from the register value `value`.
+    pub(crate) fn get(self, value: u32) -> u32 {
+        (value & self.mask) >> self.mask.trailing_zeros()
+    }
+
+    /// Places `value` at the position of the field, failing if it does not
+    /// fit.
+    pub(crate) fn prep(self, value: u32) -> Result<u32> {
+        let shift: u32 = self.mask.trailing_zeros();
+        if value > self.mask >> shift {
+            return Err(EINVAL);
+        }
+        Ok(value << shift)
+    }
+}
+
+/// Identification register, read-only.
+pub(crate) const ID: Register = Register(0x00);
+
+/// Control register.
+pub(crate) const CTRL: Register = Register(0x04);
+/// Enables the device.
+pub(crate) const CTRL_ENABLE: Field = Field::new(CTRL, 1 << 0);
+/// Selects the low-power mode.
+pub(crate) const CTRL_LOW_POWER: Field = Field::new(CTRL, 1 << 1);
+/// Number of active channels.
+pub(crate) const CTRL_CHANNELS: Field = Field::new(CTRL, 0xf << 4);
+
+/// Status register, read-only.
+pub(crate) const STATUS: Register = Register(0x08);
+/// Set while the device is enabled.
+pub(crate) const STATUS_READY: Field = Field::new(STATUS, 1 << 0);
+
+/// Number of channel threshold registers.
+pub(crate) const NR_THRESHOLDS: usize = 8;
+
+/// Threshold register of channel `channel`.
+pub(crate) fn threshold(channel: usize) -> Result<Register> {
+    if channel >= NR_THRESHOLDS {
+        return Err(EINVAL);
+    }
+    Ok(Register(0x10 + channel * 4))
+}
+
+/// In-memory stand-in for the register block, mimicking the side effects of
+/// the device.
+struct Simulated {
+    regs: [AtomicU32; SIZE / 4],
+}
+
+impl Simulated {
+    fn new() -> Self {
+        let sim: Simulated = Simulated {
+            regs: [const { AtomicU32::new(0) }; SIZE / 4],
+        };
+        () = sim.regs[ID.0 / 4].store(ID_VALUE, Ordering::Relaxed);
+        sim
+    }
+
+    fn read(&self, offset: usize) -> Result<u32> {
+        let reg: &AtomicU32 = self.regs.get(offset / 4).ok_or(EINVAL)?;
+        Ok(reg.load(Ordering::Relaxed))
+    }
+
+    fn write(&self, value: u32, offset: usize) -> Result {
+        match offset {
+            o if o == ID.0 || o == STATUS.0 => {}
+            o if o == CTRL.0 => {
+                let ready: u32 = STATUS_READY.prep(CTRL_ENABLE.get(value))?;
+                () = self.regs[CTRL.0 / 4].store(value, Ordering::Relaxed);
+                () = self.regs[STATUS.0 / 4].store(ready, Ordering::Relaxed);
+            }
+            _ => self
+                .regs
+                .get(offset / 4)
+                .ok_or(EINVAL)?
+                .store(value, Ordering::Relaxed),
+        }
+        Ok(())
+    }
+}
+
+enum Backing {
+    Mmio(Pin<KBox<Devres<IoMem<SIZE>>>>),
+    Simulated(Simulated),
+}
+
+impl Backing {
+    fn new(pdev: &platform::Device<Core>) -> Result<Self> {
+        let Some(request) = pdev.io_request_by_index(0) else {
+            return Ok(Backing::Simulated(Simulated::new()));
+        };
+
+        let mmio: Pin<KBox<Devres<IoMem<SIZE>>>> =
+            KBox::pin_init(request.iomap_sized::<SIZE>(), GFP_KERNEL)?;
+        Ok(Backing::Mmio(mmio))
+    }
+}
+
+/// Typed access to the register block of a device.
+#[pin_data]
+pub(crate) struct Regs {
+    backing: Backing,
+    /// Serializes read-modify-write sequences.
+    #[pin]
+    lock: SpinLock<()>,
+}
+
+impl Regs {
+    /// Maps the `reg` resource of `pdev`, or simulates the register block if
+    /// it has none.
+    pub(crate) fn new(pdev: &platform::Device<Core>) -> impl PinInit<Self, Error> {
+        try_pin_init!(Self {
+            backing: Backing::new(pdev)?,
+            lock <- new_spinlock!(()),
+        })
+    }
+
+    /// Whether the register block is simulated.
+    pub(crate) fn is_simulated(&self) -> bool {
+        matches!(self.backing, Backing::Simulated(_))
+    }
+
+    /// Reads `reg`, failing with `ENXIO` once a mapped device is unbound.
+    pub(crate) fn read(&self, reg: Register) -> Result<u32> {
+        match &self.backing {
+            Backing::Mmio(mmio) => mmio.try_access().ok_or(ENXIO)?.try_read32(reg.0),
+            Backing::Simulated(sim) => sim.read(reg.0),
+        }
+    }
+
+    /// Writes `value` to `reg`, failing with `ENXIO` once a mapped device is
+    /// unbound.
+    pub(crate) fn write(&self, reg: Register, value: u32) -> Result {
+        match &self.backing {
+            Backing::Mmio(mmio) => mmio.try_access().ok_or(ENXIO)?.try_write32(value, reg.0),
+            Backing::Simulated(sim) => sim.write(value, reg.0),
+        }
+    }
+
+    /// Reads `field`.
+    pub(crate) fn get(&self, field: Field) -> Result<u32> {
+        Ok(field.get(self.read(field.reg)?))
+    }
+
+    /// Sets `field` to `value`, leaving the other bits of its register alone.
+    pub(crate) fn set(&self, field: Field, value: u32) -> Result {
+        let bits: u32 = field.prep(value)?;
+        self.update(field.reg, field.mask, bits)
+    }
+
+    /// Replaces the bits of `reg` selected by `mask` with those of `bits`.
+    pub(crate) fn update(&self, reg: Register, mask: u32, bits: u32) -> Result {
+        let _guard: Guard<'_, (), SpinLockBackend> = self.lock.lock();
+        let value: u32 = self.read(reg)?;
+        self.write(reg, (value & !mask) | (bits & mask))
+    }
+}
@@ -0,0 +1,21 @@
+/* SPDX-License-Identifier: GPL-2.0 */
+
+/*
+ * C helpers of the rust_driver_platform module, called from Rust. Keep in
+ * sync with the extern block in gpio.rs.
+ */
+
+#ifndef RUST_DRIVER_PLATFORM_H
+#define RUST_DRIVER_PLATFORM_H
+
+struct device;
+struct rust_driver_platform_gpio;
+
+struct rust_driver_platform_gpio *rust_driver_platform_gpio_get(struct device *dev);
+int rust_driver_platform_gpio_set_enable(struct rust_driver_platform_gpio *gpio,
+					 int value);
+int rust_driver_platform_gpio_enable(struct rust_driver_platform_gpio *gpio);
+int rust_driver_platform_gpio_level(struct rust_driver_platform_gpio *gpio);
+unsigned int rust_driver_platform_gpio_edges(struct rust_driver_platform_gpio *gpio);
+
+#endif /* RUST_DRIVER_PLATFORM_H */
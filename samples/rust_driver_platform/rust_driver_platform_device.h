@@ -0,0 +1,26 @@
+/* SPDX-License-Identifier: GPL-2.0 */
+
+/*
+ * C helpers of the rust_driver_platform_device module, used from Rust. Keep
+ * in sync with the extern block in rust_driver_platform_device_main.rs.
+ */
+
+#ifndef RUST_DRIVER_PLATFORM_DEVICE_H
+#define RUST_DRIVER_PLATFORM_DEVICE_H
+
+#include <linux/property.h>
+
+/* rust_driver_platform_device_props.c */
+extern const struct property_entry rust_driver_platform_device_properties[];
+extern const struct property_entry rust_driver_platform_device_properties_fw[];
+
+/* rust_driver_platform_device_irq.c */
+int rust_driver_platform_device_irq_init(unsigned int nr_irqs);
+int rust_driver_platform_device_irq(unsigned int id);
+void rust_driver_platform_device_irq_exit(void);
+
+/* rust_driver_platform_device_gpio.c */
+void rust_driver_platform_device_gpio_add(void);
+void rust_driver_platform_device_gpio_remove(void);
+
+#endif /* RUST_DRIVER_PLATFORM_DEVICE_H */
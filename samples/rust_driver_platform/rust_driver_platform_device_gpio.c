@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: GPL-2.0
+
+/*
+ * GPIO lookup connecting rust_driver_platform.0 to the first two lines of
+ * the gpio-sim chip labelled "rust-sim", see gpio-sim.sh.
+ */
+
+#include <linux/gpio/machine.h>
+
+#include "rust_driver_platform_device.h"
+
+static struct gpiod_lookup_table rust_driver_platform_device_gpios = {
+	.dev_id = "rust_driver_platform.0",
+	.table = {
+		GPIO_LOOKUP("rust-sim", 0, "enable", GPIO_ACTIVE_HIGH),
+		GPIO_LOOKUP("rust-sim", 1, "event", GPIO_ACTIVE_HIGH),
+		{ }
+	},
+};
+
+void rust_driver_platform_device_gpio_add(void)
+{
+	gpiod_add_lookup_table(&rust_driver_platform_device_gpios);
+}
+
+void rust_driver_platform_device_gpio_remove(void)
+{
+	gpiod_remove_lookup_table(&rust_driver_platform_device_gpios);
+}
@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: GPL-2.0
+
+/*
+ * Simulated interrupts of the devices registered by
+ * rust_driver_platform_device.
+ *
+ * Each device gets one interrupt of an irq_sim domain. Writing a device id to
+ * /sys/kernel/debug/rust_driver_platform_device/fire raises its interrupt:
+ *
+ *   echo 0 > /sys/kernel/debug/rust_driver_platform_device/fire
+ */
+
+#include <linux/debugfs.h>
+#include <linux/err.h>
+#include <linux/fs.h>
+#include <linux/interrupt.h>
+#include <linux/irq_sim.h>
+#include <linux/irqdomain.h>
+#include <linux/kstrtox.h>
+
+#include "rust_driver_platform_device.h"
+
+static struct irq_domain *rust_driver_platform_device_domain;
+static unsigned int rust_driver_platform_device_nr_irqs;
+static struct dentry *rust_driver_platform_device_dir;
+
+static ssize_t fire_write(struct file *file, const char __user *buf,
+			  size_t count, loff_t *ppos)
+{
+	unsigned int id;
+	int virq, ret;
+
+	ret = kstrtouint_from_user(buf, count, 0, &id);
+	if (ret)
+		return ret;
+
+	if (id >= rust_driver_platform_device_nr_irqs)
+		return -EINVAL;
+
+	virq = irq_find_mapping(rust_driver_platform_device_domain, id);
+	if (!virq)
+		return -ENOENT;
+
+	ret = irq_set_irqchip_state(virq, IRQCHIP_STATE_PENDING, true);
+	if (ret)
+		return ret;
+
+	return count;
+}
+
+static const struct file_operations fire_fops = {
+	.owner = THIS_MODULE,
+	.write = fire_write,
+};
+
+/* Creates the domain with `nr_irqs` interrupts and the debugfs trigger. */
+int rust_driver_platform_device_irq_init(unsigned int nr_irqs)
+{
+	struct irq_domain *domain;
+
+	domain = irq_domain_create_sim(NULL, nr_irqs);
+	if (IS_ERR(domain))
+		return PTR_ERR(domain);
+
+	rust_driver_platform_device_domain = domain;
+	rust_driver_platform_device_nr_irqs = nr_irqs;
+
+	rust_driver_platform_device_dir =
+		debugfs_create_dir("rust_driver_platform_device", NULL);
+	debugfs_create_file("fire", 0200, rust_driver_platform_device_dir,
+			    NULL, &fire_fops);
+
+	return 0;
+}
+
+/* Returns the Linux interrupt number of device `id`, or a negative errno. */
+int rust_driver_platform_device_irq(unsigned int id)
+{
+	int virq;
+
+	if (id >= rust_driver_platform_device_nr_irqs)
+		return -EINVAL;
+
+	virq = irq_create_mapping(rust_driver_platform_device_domain, id);
+	if (!virq)
+		return -ENOMEM;
+
+	return virq;
+}
+
+/* Disposes the mappings created by rust_driver_platform_device_irq(). */
+static void rust_driver_platform_device_dispose_mappings(void)
+{
+	unsigned int id;
+	int virq;
+
+	for (id = 0; id < rust_driver_platform_device_nr_irqs; id++) {
+		virq = irq_find_mapping(rust_driver_platform_device_domain, id);
+		if (virq)
+			irq_dispose_mapping(virq);
+	}
+}
+
+/*
+ * Undoes rust_driver_platform_device_irq_init(), including when module init
+ * fails after mapping only some of the interrupts.
+ */
+void rust_driver_platform_device_irq_exit(void)
+{
+	debugfs_remove_recursive(rust_driver_platform_device_dir);
+	rust_driver_platform_device_dispose_mappings();
+	irq_domain_remove_sim(rust_driver_platform_device_domain);
+}
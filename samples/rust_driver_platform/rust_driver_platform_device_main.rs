@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Companion of the Rust Platform driver sample.
+//!
+//! Registers `nr_devices` platform devices named after the driver, each with a
+//! software node carrying the properties the driver reads. The platform bus
+//! then binds `rust_driver_platform` by name, so probe and removal can be
+//! exercised on machines without a matching device tree or ACPI node.
+//! `nr_devices` is limited to the eight misc device slots of the driver, so
+//! that every device it registers can be bound.
+//!
+//! Every device also gets a simulated interrupt, raised by writing its id to
+//! `/sys/kernel/debug/rust_driver_platform_device/fire`.
+//!
+//! With `firmware_thresholds=1`, the devices have no `test,thresholds`, so
+//! the driver loads them from its firmware blob.
+//!
+//! With `gpio_sim=1`, the first device gets the lines of the `gpio-sim` chip
+//! labelled `rust-sim`, which `gpio-sim.sh` creates.
+
+use kernel::{
+    Module, ThisModule,
+    alloc::{flags::GFP_KERNEL, kvec::KVec},
+    bindings, c_str,
+    error::{Result, code::EINVAL, from_err_ptr, to_result},
+    ffi::{c_int, c_uint},
+    macros::module,
+    pr_err, pr_info,
+    str::CStr,
+};
+
+/// Name of the devices, which must match the name of the driver.
+const NAME: &CStr = c_str!("rust_driver_platform");
+
+/// Number of misc device slots of the driver, see its `misc.rs`.
+const MAX_DEVICES: u32 = 8;
+
+// Declared in `rust_driver_platform_device.h`.
+extern "C" {
+    /// Properties of the software node, terminated by an empty entry.
+    static rust_driver_platform_device_properties: bindings::property_entry;
+    /// Same, without `test,thresholds`.
+    static rust_driver_platform_device_properties_fw: bindings::property_entry;
+
+    fn rust_driver_platform_device_irq_init(nr_irqs: c_uint) -> c_int;
+    fn rust_driver_platform_device_irq(id: c_uint) -> c_int;
+    fn rust_driver_platform_device_irq_exit();
+
+    fn rust_driver_platform_device_gpio_add();
+    fn rust_driver_platform_device_gpio_remove();
+}
+
+/// The GPIO lookup table of the first device, removed on drop.
+struct GpioLookup;
+
+impl GpioLookup {
+    fn new() -> Self {
+        // SAFETY: Only called from module init, and undone on drop.
+        () = unsafe { rust_driver_platform_device_gpio_add() };
+        GpioLookup
+    }
+}
+
+impl Drop for GpioLookup {
+    fn drop(&mut self) {
+        // SAFETY: The table was added in `GpioLookup::new`.
+        () = unsafe { rust_driver_platform_device_gpio_remove() };
+    }
+}
+
+/// The simulated interrupt domain, removed on drop.
+struct IrqSim;
+
+impl IrqSim {
+    fn new(nr_irqs: u32) -> Result<Self> {
+        // SAFETY: Only called from module init, and undone on drop.
+        () = to_result(unsafe { rust_driver_platform_device_irq_init(nr_irqs) })?;
+        Ok(IrqSim)
+    }
+
+    /// Returns the Linux interrupt number of device `id`.
+    fn irq(&self, id: u32) -> Result<u32> {
+        // SAFETY: The domain exists as long as `self`.
+        let irq: c_int = unsafe { rust_driver_platform_device_irq(id) };
+        () = to_result(irq)?;
+        Ok(irq as u32)
+    }
+}
+
+impl Drop for IrqSim {
+    fn drop(&mut self) {
+        // SAFETY: The domain was created in `IrqSim::new`.
+        () = unsafe { rust_driver_platform_device_irq_exit() };
+    }
+}
+
+/// A registered platform device, unregistered on drop.
+struct Device(*mut bindings::platform_device);
+
+// SAFETY: A platform device can be unregistered from any thread.
+unsafe impl Send for Device {}
+
+// SAFETY: `Device` offers no access to the platform device through `&self`.
+unsafe impl Sync for Device {}
+
+impl Device {
+    fn register(id: u32, irq: u32, properties: &'static bindings::property_entry) -> Result<Self> {
+        // SAFETY: All-zeroes is a valid `resource`.
+        let mut res: bindings::resource = unsafe { core::mem::zeroed() };
+        res.start = irq.into();
+        res.end = irq.into();
+        res.flags = bindings::IORESOURCE_IRQ.into();
+
+        // SAFETY: All-zeroes is a valid `platform_device_info`.
+        let mut info: bindings::platform_device_info = unsafe { core::mem::zeroed() };
+        info.name = NAME.as_char_ptr();
+        info.id = id as c_int;
+        info.res = &res;
+        info.num_res = 1;
+        info.properties = properties;
+
+        // SAFETY: `info` and `res` are valid for the duration of the call,
+        // which copies the resource, and the name and properties are static.
+        let pdev: *mut bindings::platform_device =
+            from_err_ptr(unsafe { bindings::platform_device_register_full(&info) })?;
+
+        Ok(Device(pdev))
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        // SAFETY: `self.0` was registered in `Device::register`.
+        () = unsafe { bindings::platform_device_unregister(self.0) };
+    }
+}
+
+struct RustDriverPlatformDevice {
+    // Unregistered before the interrupt domain and GPIO lookup are removed.
+    _devices: KVec<Device>,
+    _irq_sim: IrqSim,
+    _gpio_lookup: Option<GpioLookup>,
+}
+
+impl Module for RustDriverPlatformDevice {
+    fn init(_module: &'static ThisModule) -> Result<Self> {
+        let nr_devices: u32 = *module_parameters::nr_devices.value();
+        if nr_devices > MAX_DEVICES {
+            pr_err!("nr_devices must be at most {MAX_DEVICES}\n");
+            return Err(EINVAL);
+        }
+        pr_info!("Registering {nr_devices} {NAME} device(s)\n");
+
+        let irq_sim: IrqSim = IrqSim::new(nr_devices)?;
+
+        // Added before the devices, so that their probe finds it.
+        let gpio_lookup: Option<GpioLookup> =
+            (*module_parameters::gpio_sim.value() != 0).then(GpioLookup::new);
+
+        // SAFETY: The C arrays are never modified.
+        let properties: &'static bindings::property_entry = unsafe {
+            if *module_parameters::firmware_thresholds.value() != 0 {
+                &rust_driver_platform_device_properties_fw
+            } else {
+                &rust_driver_platform_device_properties
+            }
+        };
+
+        let mut devices: KVec<Device> = KVec::with_capacity(nr_devices as usize, GFP_KERNEL)?;
+        for id in 0..nr_devices {
+            () = devices.push(
+                Device::register(id, irq_sim.irq(id)?, properties)?,
+                GFP_KERNEL,
+            )?;
+        }
+
+        Ok(RustDriverPlatformDevice {
+            _devices: devices,
+            _irq_sim: irq_sim,
+            _gpio_lookup: gpio_lookup,
+        })
+    }
+}
+
+module! {
+    type: RustDriverPlatformDevice,
+    name: "rust_driver_platform_device",
+    authors: ["Danilo Krummrich"],
+    description: "Rust Platform driver sample devices",
+    license: "GPL v2",
+    params: {
+        nr_devices: u32 {
+            default: 1,
+            description: "Number of devices to register, at most 8",
+        },
+        firmware_thresholds: u32 {
+            default: 0,
+            description: "Omit 'test,thresholds' so that the driver loads them from firmware",
+        },
+        gpio_sim: u32 {
+            default: 0,
+            description: "Connect the first device to the gpio-sim chip 'rust-sim'",
+        },
+    },
+}
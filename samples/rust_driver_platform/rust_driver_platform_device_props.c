@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: GPL-2.0
+
+#include <linux/property.h>
+
+#include "rust_driver_platform_device.h"
+
+static const u32 rust_driver_platform_device_thresholds[] = { 10, 20, 30, 40 };
+
+/* Same properties as rust_driver_platform.dtso. */
+const struct property_entry rust_driver_platform_device_properties[] = {
+	PROPERTY_ENTRY_STRING("label", "rust-device-swnode"),
+	PROPERTY_ENTRY_U32("test,channels", 4),
+	PROPERTY_ENTRY_U32_ARRAY("test,thresholds",
+				 rust_driver_platform_device_thresholds),
+	PROPERTY_ENTRY_BOOL("test,low-power"),
+	{ }
+};
+
+/*
+ * Without test,thresholds, so that the driver loads its thresholds from the
+ * rust_driver_platform.bin firmware blob. Used with firmware_thresholds=1.
+ */
+const struct property_entry rust_driver_platform_device_properties_fw[] = {
+	PROPERTY_ENTRY_STRING("label", "rust-device-swnode-fw"),
+	PROPERTY_ENTRY_U32("test,channels", 4),
+	{ }
+};
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: GPL-2.0
+
+/*
+ * GPIO lines of the Rust Platform driver sample.
+ *
+ * There are no Rust GPIO consumer abstractions yet, so the lines are acquired
+ * here, device-managed, and driven from Rust through the functions below. The
+ * `enable` line is an output, the `event` line an input whose edges are
+ * counted.
+ */
+
+#include <linux/atomic.h>
+#include <linux/device.h>
+#include <linux/err.h>
+#include <linux/gpio/consumer.h>
+#include <linux/interrupt.h>
+
+#include "rust_driver_platform.h"
+
+struct rust_driver_platform_gpio {
+	struct device *dev;
+	struct gpio_desc *enable;
+	struct gpio_desc *event;
+	int irq;
+	atomic_t edges;
+	int level;
+};
+
+static irqreturn_t rust_driver_platform_gpio_edge(int irq, void *data)
+{
+	struct rust_driver_platform_gpio *gpio = data;
+	int level = gpiod_get_value_cansleep(gpio->event);
+
+	WRITE_ONCE(gpio->level, level);
+	atomic_inc(&gpio->edges);
+	dev_info(gpio->dev, "Edge on event line, level %d.\n", level);
+
+	return IRQ_HANDLED;
+}
+
+/*
+ * Acquires the lines of `dev` until it is unbound. Returns NULL if it has
+ * none, or an ERR_PTR if only some of them are present or they cannot be set
+ * up.
+ */
+struct rust_driver_platform_gpio *rust_driver_platform_gpio_get(struct device *dev)
+{
+	struct rust_driver_platform_gpio *gpio;
+	struct gpio_desc *enable, *event;
+	int ret;
+
+	enable = devm_gpiod_get_optional(dev, "enable", GPIOD_OUT_LOW);
+	if (IS_ERR(enable))
+		return ERR_CAST(enable);
+
+	event = devm_gpiod_get_optional(dev, "event", GPIOD_IN);
+	if (IS_ERR(event))
+		return ERR_CAST(event);
+
+	if (!enable && !event)
+		return NULL;
+
+	if (!enable || !event) {
+		dev_err(dev, "Needs both 'enable' and 'event' GPIOs.\n");
+		return ERR_PTR(-EINVAL);
+	}
+
+	gpio = devm_kzalloc(dev, sizeof(*gpio), GFP_KERNEL);
+	if (!gpio)
+		return ERR_PTR(-ENOMEM);
+
+	gpio->dev = dev;
+	gpio->enable = enable;
+	gpio->event = event;
+	gpio->level = gpiod_get_value_cansleep(event);
+
+	gpio->irq = gpiod_to_irq(event);
+	if (gpio->irq < 0)
+		return ERR_PTR(gpio->irq);
+
+	/* Freed before `gpio`, as devres releases in reverse order. */
+	ret = devm_request_threaded_irq(dev, gpio->irq, NULL,
+					rust_driver_platform_gpio_edge,
+					IRQF_TRIGGER_RISING |
+					IRQF_TRIGGER_FALLING | IRQF_ONESHOT,
+					dev_name(dev), gpio);
+	if (ret)
+		return ERR_PTR(ret);
+
+	return gpio;
+}
+
+int rust_driver_platform_gpio_set_enable(struct rust_driver_platform_gpio *gpio,
+					 int value)
+{
+	return gpiod_set_value_cansleep(gpio->enable, value);
+}
+
+int rust_driver_platform_gpio_enable(struct rust_driver_platform_gpio *gpio)
+{
+	return gpiod_get_value_cansleep(gpio->enable);
+}
+
+int rust_driver_platform_gpio_level(struct rust_driver_platform_gpio *gpio)
+{
+	return READ_ONCE(gpio->level);
+}
+
+unsigned int rust_driver_platform_gpio_edges(struct rust_driver_platform_gpio *gpio)
+{
+	return atomic_read(&gpio->edges);
+}
@@ -0,0 +1,334 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Rust Platform driver sample.
+//!
+//! The MMIO mapping and the GPIO lines with their interrupt are
+//! device-managed and released by devres after unbind. The interrupt, the
+//! misc device and the simulated register block are owned by the driver data
+//! and released when it is dropped. The sysfs group and the PM domain cannot
+//! be device-managed: their callbacks reach the driver data, which is dropped
+//! before devres runs, so `unbind` removes them while it is still alive.
+
+use {
+    core::pin::Pin,
+    kernel::{
+        acpi, acpi_device_table,
+        alloc::{flags::GFP_KERNEL, kbox::KBox},
+        bindings, c_str, dev_dbg, dev_err, dev_info, dev_warn,
+        device::{self, Core},
+        error::{
+            Error, Result,
+            code::{EINVAL, ENODEV},
+        },
+        miscdevice::{MiscDeviceOptions, MiscDeviceRegistration},
+        module_platform_driver, of, of_device_table, platform, try_pin_init,
+        types::ARef,
+    },
+    pin_init::{pin_data, pinned_drop},
+};
+
+mod chip;
+mod config;
+mod firmware;
+mod gpio;
+mod interrupt;
+mod misc;
+mod pm;
+mod regs;
+mod sysfs;
+mod util;
+
+#[pin_data(PinnedDrop)]
+struct SampleDriver {
+    /// Dropped first, so that opening the misc device can access the rest
+    /// of the driver.
+    #[pin]
+    misc: MiscDeviceRegistration<misc::Instance>,
+    minor: misc::Minor,
+    pdev: ARef<platform::Device>,
+    /// Match table entry, if the device was not bound by name.
+    info: Option<Info>,
+    chip: &'static chip::Chip,
+    /// Monotonic time of probe, in nanoseconds.
+    probed_at: i64,
+    config: config::Config,
+    #[pin]
+    regs: regs::Regs,
+    irq: Option<interrupt::Registration>,
+    gpio: Option<gpio::Gpio>,
+    pm: pm::Pm,
+}
+
+/// Firmware interface a device was matched through.
+#[derive(Clone, Copy, Debug)]
+enum Firmware {
+    Of,
+    Acpi,
+}
+
+#[derive(Clone, Copy)]
+struct Info {
+    firmware: Firmware,
+    chip: &'static chip::Chip,
+}
+
+of_device_table!(
+    OF_TABLE,
+    MODULE_OF_TABLE,
+    <SampleDriver as platform::Driver>::IdInfo,
+    [
+        (
+            of::DeviceId::new(c_str!("test,rust-device-v1")),
+            Info {
+                firmware: Firmware::Of,
+                chip: &chip::V1
+            }
+        ),
+        (
+            of::DeviceId::new(c_str!("test,rust-device-v2")),
+            Info {
+                firmware: Firmware::Of,
+                chip: &chip::V2
+            }
+        ),
+        // Predates the versioned compatibles and only ever shipped on V1.
+        (
+            of::DeviceId::new(c_str!("test,rust-device")),
+            Info {
+                firmware: Firmware::Of,
+                chip: &chip::V1
+            }
+        )
+    ]
+);
+
+acpi_device_table!(
+    ACPI_TABLE,
+    MODULE_ACPI_TABLE,
+    <SampleDriver as platform::Driver>::IdInfo,
+    [(
+        acpi::DeviceId::new(c_str!("LNUXBEEF")),
+        Info {
+            firmware: Firmware::Acpi,
+            chip: &chip::V2
+        }
+    )]
+);
+
+impl platform::Driver for SampleDriver {
+    type IdInfo = Info;
+
+    const OF_ID_TABLE: Option<of::IdTable<Self::IdInfo>> = Some(&OF_TABLE);
+    const ACPI_ID_TABLE: Option<acpi::IdTable<Self::IdInfo>> = Some(&ACPI_TABLE);
+
+    fn probe(
+        pdev: &platform::Device<Core>,
+        info: Option<&Self::IdInfo>,
+    ) -> Result<Pin<KBox<Self>>> {
+        dev_dbg!(pdev.as_ref(), "Probe Rust Platform driver sample.\n");
+
+        let dev: &device::Device<Core> = pdev.as_ref();
+
+        // Devices bound by name, like those of `rust_driver_platform_device`,
+        // are the most capable variant.
+        let chip: &'static chip::Chip = match info {
+            Some(info) => {
+                dev_info!(dev, "Probed through {:?}.\n", info.firmware);
+                info.chip
+            }
+            None => {
+                dev_info!(dev, "Probed without a match table entry.\n");
+                &chip::V2
+            }
+        };
+        dev_info!(dev, "Chip: {chip:?}.\n");
+
+        let mut config: config::Config = config::Config::parse(dev)?;
+        () = Self::check(dev, chip, &config)?;
+        if config.thresholds.is_empty() && chip.features.contains(chip::Features::THRESHOLDS) {
+            config.thresholds = firmware::thresholds(dev, config.channels)?;
+        }
+        dev_info!(dev, "Probed with config: {config:?}.\n");
+
+        let minor: misc::Minor = misc::Minor::alloc().inspect_err(|_: &Error| {
+            dev_err!(
+                dev,
+                "All {} misc device slots are in use.\n",
+                misc::Minor::MAX
+            );
+        })?;
+        let options: MiscDeviceOptions = minor.options();
+
+        let drvdata: Pin<KBox<SampleDriver>> = KBox::pin_init(
+            try_pin_init!(Self {
+                pdev: ARef::from(pdev),
+                info: info.copied(),
+                chip,
+                // SAFETY: `ktime_get` has no safety requirements.
+                probed_at: unsafe { bindings::ktime_get() },
+                config,
+                regs <- regs::Regs::new(pdev),
+                irq: if chip.features.contains(chip::Features::IRQ) {
+                    interrupt::request(pdev)?
+                } else {
+                    None
+                },
+                gpio: gpio::Gpio::get(dev)?,
+                pm: pm::Pm::new(),
+                minor,
+                // Registered last, as opening it accesses the other fields.
+                misc <- MiscDeviceRegistration::register(options),
+            }),
+            GFP_KERNEL,
+        )?;
+
+        () = drvdata.enable()?;
+        () = drvdata.pm.enable(dev)?;
+        // `unbind` is not called if probe fails.
+        () = sysfs::create(dev).inspect_err(|_: &Error| drvdata.pm.disable(dev))?;
+
+        Ok(drvdata)
+    }
+
+    fn unbind(pdev: &platform::Device<Core>, this: Pin<&Self>) {
+        let dev: &device::Device<Core> = pdev.as_ref();
+
+        // Attribute and PM callbacks reach the driver data through the
+        // device, so they must be gone before it is dropped. Both wait for
+        // running callbacks.
+        () = sysfs::remove(dev);
+        () = this.pm.disable(dev);
+    }
+}
+
+impl SampleDriver {
+    /// Checks that `config` fits the limits of `chip`.
+    fn check(dev: &device::Device, chip: &chip::Chip, config: &config::Config) -> Result {
+        if config.channels > chip.max_channels {
+            dev_err!(
+                dev,
+                "{:?} supports up to {} channels, got {}.\n",
+                chip.revision,
+                chip.max_channels,
+                config.channels
+            );
+            return Err(EINVAL);
+        }
+
+        if config.low_power && !chip.features.contains(chip::Features::LOW_POWER) {
+            dev_warn!(
+                dev,
+                "{:?} has no low-power mode, ignoring it.\n",
+                chip.revision
+            );
+        }
+
+        if !config.thresholds.is_empty() && !chip.features.contains(chip::Features::THRESHOLDS) {
+            dev_warn!(
+                dev,
+                "{:?} has no thresholds, ignoring them.\n",
+                chip.revision
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the driver bound to `dev`.
+    ///
+    /// # Safety
+    ///
+    /// `dev` must be a device this driver may be bound to, and the caller
+    /// must ensure the driver is not dropped while the returned reference is
+    /// used.
+    unsafe fn from_raw<'a>(dev: *mut bindings::device) -> Result<&'a Self> {
+        // SAFETY: `dev` is valid by the safety requirements.
+        let data: *const Self = unsafe { bindings::dev_get_drvdata(dev) }.cast();
+        if data.is_null() {
+            // Probe has not returned yet.
+            return Err(ENODEV);
+        }
+
+        // SAFETY: The driver core stores the `Pin<KBox<SampleDriver>>`
+        // returned by probe as driver data, and it stays alive by the safety
+        // requirements.
+        Ok(unsafe { &*data })
+    }
+
+    /// Checks the identification of the device and programs it from the
+    /// configuration.
+    fn enable(&self) -> Result {
+        let dev: &device::Device = self.pdev.as_ref();
+        let regs: &regs::Regs = &self.regs;
+
+        let id: u32 = regs.read(regs::ID)?;
+        if id != regs::ID_VALUE {
+            dev_err!(dev, "Unknown device id {id:#010x}.\n");
+            return Err(ENODEV);
+        }
+
+        let features: chip::Features = self.chip.features;
+
+        if features.contains(chip::Features::THRESHOLDS) {
+            for (channel, threshold) in self.config.thresholds.iter().enumerate() {
+                () = regs.write(regs::threshold(channel)?, *threshold)?;
+            }
+        }
+
+        () = regs.set(regs::CTRL_CHANNELS, self.config.channels)?;
+        if features.contains(chip::Features::LOW_POWER) {
+            () = regs.set(regs::CTRL_LOW_POWER, u32::from(self.config.low_power))?;
+        }
+        () = regs.set(regs::CTRL_ENABLE, 1)?;
+        if let Some(gpio) = &self.gpio {
+            () = gpio.set_enable(true)?;
+        }
+
+        let ready: u32 = regs.get(regs::STATUS_READY)?;
+        dev_info!(
+            dev,
+            "Enabled {} registers, ready: {ready}.\n",
+            if regs.is_simulated() {
+                "simulated"
+            } else {
+                "mapped"
+            }
+        );
+
+        Ok(())
+    }
+}
+
+#[pinned_drop]
+impl PinnedDrop for SampleDriver {
+    fn drop(self: Pin<&mut Self>) {
+        // The attributes and the PM domain are already gone, see `unbind`.
+        // Nothing can be done about a failure to disable the device here.
+        let _ = self.regs.set(regs::CTRL_ENABLE, 0);
+        if let Some(gpio) = &self.gpio {
+            let _ = gpio.set_enable(false);
+        }
+
+        if let Some(irq) = &self.irq {
+            let (raised, handled): (u64, u64) = irq.handler().counts();
+            dev_info!(
+                self.pdev.as_ref(),
+                "{raised} interrupt(s) raised, {handled} handled.\n"
+            );
+        }
+
+        dev_dbg!(
+            self.pdev.as_ref(),
+            "Remove Rust Platform driver sample ({}).\n",
+            self.config.label
+        );
+    }
+}
+
+module_platform_driver! {
+    type: SampleDriver,
+    name: "rust_driver_platform",
+    authors: ["Danilo Krummrich"],
+    description: "Rust Platform driver",
+    license: "GPL v2",
+}
@@ -0,0 +1,83 @@
+#!/bin/sh
+# SPDX-License-Identifier: GPL-2.0
+#
+# Binds rust_driver_platform to devices registered by
+# rust_driver_platform_device, raises their simulated interrupts and removes
+# them again, then checks thresholds loaded from a firmware blob. Run as root
+# from the directory holding the built modules and mkfirmware, with debugfs
+# mounted.
+
+set -e
+
+DRIVER=/sys/bus/platform/drivers/rust_driver_platform
+NR_DEVICES=${NR_DEVICES:-2}
+
+insmod rust_driver_platform.ko
+insmod rust_driver_platform_device.ko nr_devices="$NR_DEVICES"
+
+for id in $(seq 0 $((NR_DEVICES - 1))); do
+	if [ ! -e "$DRIVER/rust_driver_platform.$id" ]; then
+		echo "rust_driver_platform.$id is not bound" >&2
+		exit 1
+	fi
+done
+
+# The N of /dev/rust-platform-N is a slot taken at probe time, not the
+# platform device id, so only the number of nodes is checked.
+NODES=$(find /dev -maxdepth 1 -name 'rust-platform-*' -type c | wc -l)
+if [ "$NODES" -ne "$NR_DEVICES" ]; then
+	echo "$NODES misc devices for $NR_DEVICES devices" >&2
+	exit 1
+fi
+
+DEV=/sys/bus/platform/devices/rust_driver_platform.0
+cat "$DEV/info" "$DEV/probed_at"
+echo normal > "$DEV/mode"
+echo low-power > "$DEV/mode"
+if [ "$(cat "$DEV/mode")" != "low-power" ]; then
+	echo "mode was not stored" >&2
+	exit 1
+fi
+
+sleep 2
+if [ "$(cat "$DEV/power/runtime_status")" != "suspended" ]; then
+	echo "rust_driver_platform.0 did not autosuspend" >&2
+	exit 1
+fi
+
+FIRE=/sys/kernel/debug/rust_driver_platform_device/fire
+for id in $(seq 0 $((NR_DEVICES - 1))); do
+	echo "$id" > "$FIRE"
+done
+sleep 1
+for id in $(seq 0 $((NR_DEVICES - 1))); do
+	IRQ=/sys/bus/platform/devices/rust_driver_platform.$id/irq
+	cat "$IRQ"
+	if ! grep -q "raised=1 handled=1" "$IRQ"; then
+		echo "interrupt of rust_driver_platform.$id was not handled" >&2
+		exit 1
+	fi
+done
+
+rmmod rust_driver_platform_device
+
+# Without test,thresholds, the thresholds come from the firmware blob, and
+# channels it does not cover get the built-in 100.
+BLOB=/lib/firmware/rust_driver_platform.bin
+if [ -e "$BLOB" ]; then
+	echo "$BLOB exists, not overwriting it" >&2
+	exit 1
+fi
+./mkfirmware "$BLOB" 11 22 33
+insmod rust_driver_platform_device.ko firmware_thresholds=1
+THRESHOLDS=$(cat /sys/bus/platform/devices/rust_driver_platform.0/thresholds)
+rmmod rust_driver_platform_device
+rm "$BLOB"
+if [ "$THRESHOLDS" != "11 22 33 100" ]; then
+	echo "thresholds from firmware are '$THRESHOLDS'" >&2
+	exit 1
+fi
+
+rmmod rust_driver_platform
+
+echo "ok"
@@ -0,0 +1,333 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! sysfs attributes of the Rust Platform driver sample.
+//!
+//! The attributes show up in `/sys/bus/platform/devices/<dev>/` while the
+//! driver is bound:
+//!
+//! - `info`: the firmware interface the device was matched through, or
+//!   `none`, followed by the chip variant.
+//! - `probed_at`: the monotonic time of probe, in nanoseconds.
+//! - `mode`: `normal` or `low-power`, writable.
+//! - `gpio`: the `enable` output, the `event` input level and the number of
+//!   edges on it. Writing `0` or `1` drives the output.
+//! - `irq`: the number of interrupts raised and handled so far.
+//! - `thresholds`: the threshold of every channel, from the firmware node or
+//!   the firmware blob.
+
+use {
+    crate::{
+        SampleDriver, chip, gpio, interrupt, pm, regs,
+        util::{Table, to_ssize},
+    },
+    core::fmt::{self, Write},
+    kernel::{
+        bindings, device,
+        error::{
+            Error, Result,
+            code::{EFBIG, EINVAL, ENODEV},
+            to_result,
+        },
+        ffi::c_char,
+        page::PAGE_SIZE,
+    },
+};
+
+macro_rules! attribute {
+    ($static:ident, $name:literal, $mode:literal, $show:expr, $store:expr) => {
+        static $static: Table<bindings::device_attribute> = Table(bindings::device_attribute {
+            attr: bindings::attribute {
+                name: $name.as_ptr(),
+                mode: $mode,
+                // SAFETY: The remaining lockdep fields are valid when zeroed.
+                ..unsafe { core::mem::zeroed() }
+            },
+            show: $show,
+            store: $store,
+        });
+    };
+}
+
+attribute!(INFO, c"info", 0o444, Some(show_info), None);
+attribute!(PROBED_AT, c"probed_at", 0o444, Some(show_probed_at), None);
+attribute!(MODE, c"mode", 0o644, Some(show_mode), Some(store_mode));
+attribute!(GPIO, c"gpio", 0o644, Some(show_gpio), Some(store_gpio));
+attribute!(IRQ, c"irq", 0o444, Some(show_irq), None);
+attribute!(
+    THRESHOLDS,
+    c"thresholds",
+    0o444,
+    Some(show_thresholds),
+    None
+);
+
+static ATTRIBUTES: Table<[*mut bindings::attribute; 7]> = Table([
+    &raw const INFO.0.attr as *mut bindings::attribute,
+    &raw const PROBED_AT.0.attr as *mut bindings::attribute,
+    &raw const MODE.0.attr as *mut bindings::attribute,
+    &raw const GPIO.0.attr as *mut bindings::attribute,
+    &raw const IRQ.0.attr as *mut bindings::attribute,
+    &raw const THRESHOLDS.0.attr as *mut bindings::attribute,
+    core::ptr::null_mut(),
+]);
+
+static GROUP: Table<bindings::attribute_group> = Table(bindings::attribute_group {
+    attrs: &raw const ATTRIBUTES.0 as *mut *mut bindings::attribute,
+    // SAFETY: All other fields of `attribute_group` are valid when zeroed.
+    ..unsafe { core::mem::zeroed() }
+});
+
+/// Adds the attributes to `dev`.
+///
+/// Must be the last fallible step of probe, so that the attributes never
+/// outlive a driver that failed to probe.
+pub(crate) fn create(dev: &device::Device<device::Core>) -> Result {
+    // SAFETY: `dev` is valid and `GROUP` is static.
+    to_result(unsafe { bindings::sysfs_create_group(&raw mut (*dev.as_raw()).kobj, &GROUP.0) })
+}
+
+/// Removes the attributes from `dev`, waiting for running callbacks.
+///
+/// Does nothing if they were not created, as `GROUP` has no directory of its
+/// own.
+pub(crate) fn remove(dev: &device::Device) {
+    // SAFETY: `dev` is valid and `GROUP` is static.
+    () = unsafe { bindings::sysfs_remove_group(&raw mut (*dev.as_raw()).kobj, &GROUP.0) };
+}
+
+struct PageWriter<'a> {
+    page: &'a mut [u8; PAGE_SIZE],
+    len: usize,
+}
+
+impl Write for PageWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end: usize = self.len + s.len();
+        if end > PAGE_SIZE {
+            return Err(fmt::Error);
+        }
+        () = self.page[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Calls `f` with the driver of `dev` and a writer into `buf`.
+///
+/// # Safety
+///
+/// Same as [`SampleDriver::from_raw`], and `buf` must be a sysfs buffer of
+/// `PAGE_SIZE` bytes.
+unsafe fn show(
+    dev: *mut bindings::device,
+    buf: *mut c_char,
+    f: impl FnOnce(&SampleDriver, &mut PageWriter<'_>) -> Result,
+) -> isize {
+    // SAFETY: By the safety requirements.
+    let (this, page): (Result<&SampleDriver>, &mut [u8; PAGE_SIZE]) = unsafe {
+        (
+            SampleDriver::from_raw(dev),
+            &mut *buf.cast::<[u8; PAGE_SIZE]>(),
+        )
+    };
+
+    let mut writer: PageWriter<'_> = PageWriter { page, len: 0 };
+    let result: Result = this.and_then(|this: &SampleDriver| -> Result { f(this, &mut writer) });
+    to_ssize(result.map(|()| -> usize { writer.len }))
+}
+
+fn emit(writer: &mut PageWriter<'_>, args: fmt::Arguments<'_>) -> Result {
+    writer
+        .write_fmt(args)
+        .map_err(|_: fmt::Error| -> Error { EFBIG })
+}
+
+unsafe extern "C" fn show_info(
+    dev: *mut bindings::device,
+    _attr: *mut bindings::device_attribute,
+    buf: *mut c_char,
+) -> isize {
+    // SAFETY: `INFO` is only part of `GROUP`, and sysfs passes a page.
+    unsafe {
+        show(
+            dev,
+            buf,
+            |this: &SampleDriver, w: &mut PageWriter<'_>| -> Result {
+                let chip: &chip::Chip = this.chip;
+                match &this.info {
+                    Some(info) => emit(w, format_args!("{:?} ", info.firmware))?,
+                    None => emit(w, format_args!("none "))?,
+                }
+                emit(
+                    w,
+                    format_args!(
+                        "{:?} features={} max_channels={}\n",
+                        chip.revision, chip.features, chip.max_channels
+                    ),
+                )
+            },
+        )
+    }
+}
+
+unsafe extern "C" fn show_probed_at(
+    dev: *mut bindings::device,
+    _attr: *mut bindings::device_attribute,
+    buf: *mut c_char,
+) -> isize {
+    // SAFETY: `PROBED_AT` is only part of `GROUP`, and sysfs passes a page.
+    unsafe {
+        show(
+            dev,
+            buf,
+            |this: &SampleDriver, w: &mut PageWriter<'_>| -> Result {
+                emit(w, format_args!("{}\n", this.probed_at))
+            },
+        )
+    }
+}
+
+unsafe extern "C" fn show_mode(
+    dev: *mut bindings::device,
+    _attr: *mut bindings::device_attribute,
+    buf: *mut c_char,
+) -> isize {
+    // SAFETY: `MODE` is only part of `GROUP`, and sysfs passes a page.
+    unsafe {
+        show(
+            dev,
+            buf,
+            |this: &SampleDriver, w: &mut PageWriter<'_>| -> Result {
+                let _active: pm::Active<'_> = this.pm.get(this.pdev.as_ref())?;
+                let mode: &str = match this.regs.get(regs::CTRL_LOW_POWER)? {
+                    0 => "normal",
+                    _ => "low-power",
+                };
+                emit(w, format_args!("{mode}\n"))
+            },
+        )
+    }
+}
+
+unsafe extern "C" fn store_mode(
+    dev: *mut bindings::device,
+    _attr: *mut bindings::device_attribute,
+    buf: *const c_char,
+    count: usize,
+) -> isize {
+    // SAFETY: `MODE` is only part of `GROUP`, and sysfs passes `count` bytes.
+    let (this, data): (Result<&SampleDriver>, &[u8]) = unsafe {
+        (
+            SampleDriver::from_raw(dev),
+            core::slice::from_raw_parts(buf.cast::<u8>(), count),
+        )
+    };
+
+    let result: Result = this.and_then(|this: &SampleDriver| -> Result {
+        let low_power: u32 = match data.trim_ascii() {
+            b"normal" => 0,
+            b"low-power" => 1,
+            _ => return Err(EINVAL),
+        };
+        if low_power != 0 && !this.chip.features.contains(chip::Features::LOW_POWER) {
+            return Err(EINVAL);
+        }
+        let _active: pm::Active<'_> = this.pm.get(this.pdev.as_ref())?;
+        this.regs.set(regs::CTRL_LOW_POWER, low_power)
+    });
+
+    to_ssize(result.map(|()| -> usize { count }))
+}
+
+unsafe extern "C" fn show_gpio(
+    dev: *mut bindings::device,
+    _attr: *mut bindings::device_attribute,
+    buf: *mut c_char,
+) -> isize {
+    // SAFETY: `GPIO` is only part of `GROUP`, and sysfs passes a page.
+    unsafe {
+        show(
+            dev,
+            buf,
+            |this: &SampleDriver, w: &mut PageWriter<'_>| -> Result {
+                let gpio: &gpio::Gpio = this.gpio.as_ref().ok_or(ENODEV)?;
+                emit(
+                    w,
+                    format_args!(
+                        "enable={} event={} edges={}\n",
+                        u8::from(gpio.enable()?),
+                        u8::from(gpio.level()),
+                        gpio.edges()
+                    ),
+                )
+            },
+        )
+    }
+}
+
+unsafe extern "C" fn store_gpio(
+    dev: *mut bindings::device,
+    _attr: *mut bindings::device_attribute,
+    buf: *const c_char,
+    count: usize,
+) -> isize {
+    // SAFETY: `GPIO` is only part of `GROUP`, and sysfs passes `count` bytes.
+    let (this, data): (Result<&SampleDriver>, &[u8]) = unsafe {
+        (
+            SampleDriver::from_raw(dev),
+            core::slice::from_raw_parts(buf.cast::<u8>(), count),
+        )
+    };
+
+    let result: Result = this.and_then(|this: &SampleDriver| -> Result {
+        let gpio: &gpio::Gpio = this.gpio.as_ref().ok_or(ENODEV)?;
+        let value: bool = match data.trim_ascii() {
+            b"0" => false,
+            b"1" => true,
+            _ => return Err(EINVAL),
+        };
+        gpio.set_enable(value)
+    });
+
+    to_ssize(result.map(|()| -> usize { count }))
+}
+
+unsafe extern "C" fn show_irq(
+    dev: *mut bindings::device,
+    _attr: *mut bindings::device_attribute,
+    buf: *mut c_char,
+) -> isize {
+    // SAFETY: `IRQ` is only part of `GROUP`, and sysfs passes a page.
+    unsafe {
+        show(
+            dev,
+            buf,
+            |this: &SampleDriver, w: &mut PageWriter<'_>| -> Result {
+                let irq: &interrupt::Registration = this.irq.as_ref().ok_or(ENODEV)?;
+                let (raised, handled): (u64, u64) = irq.handler().counts();
+                emit(w, format_args!("raised={raised} handled={handled}\n"))
+            },
+        )
+    }
+}
+
+unsafe extern "C" fn show_thresholds(
+    dev: *mut bindings::device,
+    _attr: *mut bindings::device_attribute,
+    buf: *mut c_char,
+) -> isize {
+    // SAFETY: `THRESHOLDS` is only part of `GROUP`, and sysfs passes a page.
+    unsafe {
+        show(
+            dev,
+            buf,
+            |this: &SampleDriver, w: &mut PageWriter<'_>| -> Result {
+                for (index, threshold) in this.config.thresholds.iter().enumerate() {
+                    let separator: &str = if index == 0 { "" } else { " " };
+                    () = emit(w, format_args!("{separator}{threshold}"))?;
+                }
+                emit(w, format_args!("\n"))
+            },
+        )
+    }
+}
@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Helpers shared by the modules of the Rust Platform driver sample.
+
+use kernel::error::Result;
+
+/// Wrapper allowing C tables to live in statics.
+#[repr(transparent)]
+pub(crate) struct Table<T>(pub(crate) T);
+
+// SAFETY: The wrapped tables are never mutated and only hold pointers to
+// functions and other statics.
+unsafe impl<T> Sync for Table<T> {}
+
+/// Converts the result of a C attribute callback to what sysfs expects.
+pub(crate) fn to_ssize(ret: Result<usize>) -> isize {
+    match ret {
+        Ok(len) => len as isize,
+        Err(e) => e.to_errno() as isize,
+    }
+}